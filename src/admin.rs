@@ -0,0 +1,239 @@
+use crate::config::{RouteConfig, RouteEntry};
+use crate::http::{Method, Request, Response, Status};
+use crate::journal::Journal;
+use crate::routes::{Route, RouteTable};
+use crate::verify::RequestPattern;
+use crate::{EndpointMeta, Message};
+use log::info;
+use serde_json::{json, Value};
+use std::{path::Path, sync::Arc};
+use tokio::sync::{mpsc, watch};
+
+/// Path prefix the admin API is served under unless `--admin-prefix` moves it
+pub const DEFAULT_PREFIX: &str = "/__admin";
+
+/// Control API for tests: changes the routes, reads and clears the request journal and
+/// resets scenarios while the server runs
+#[derive(Debug)]
+pub struct Admin {
+    pub prefix: String,
+    routes: Arc<watch::Sender<Arc<RouteTable>>>,
+    journal: Arc<Journal>,
+    messages: mpsc::Sender<Message>,
+    /// Defaults from the command line for posted routes
+    defaults: EndpointMeta,
+}
+
+impl Admin {
+    pub fn new(
+        prefix: &str,
+        routes: Arc<watch::Sender<Arc<RouteTable>>>,
+        journal: Arc<Journal>,
+        messages: mpsc::Sender<Message>,
+        defaults: EndpointMeta,
+    ) -> Self {
+        Admin {
+            prefix: prefix.trim_end_matches('/').to_string(),
+            routes,
+            journal,
+            messages,
+            defaults,
+        }
+    }
+
+    /// Whether `path` belongs to the admin API rather than the mocked routes
+    pub fn serves(&self, path: &str) -> bool {
+        path.strip_prefix(&self.prefix)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    }
+
+    pub async fn handle(&self, req: &Request) -> Response {
+        let path = req.path[self.prefix.len()..].trim_end_matches('/');
+        let response = match (req.method, path) {
+            (Method::GET, "/routes") => self.list_routes(),
+            (Method::POST, "/routes") => self.add_routes(req).await,
+            (Method::DELETE, "/routes") => self.remove_routes(req).await,
+            (Method::GET, "/requests") => json_response(
+                Status::OK,
+                Value::from(
+                    self.journal
+                        .exchanges()
+                        .iter()
+                        .map(|exchange| exchange.to_json())
+                        .collect::<Vec<_>>(),
+                ),
+            ),
+            (Method::POST, "/requests/find") => self.find_requests(req),
+            (Method::DELETE, "/requests") => {
+                self.clear_requests().await;
+                Response::empty().status(Status::NO_CONTENT)
+            }
+            (Method::GET, "/scenarios") => self.list_scenarios(),
+            (Method::POST, "/scenarios/reset") => {
+                self.reset_scenarios().await;
+                self.list_scenarios()
+            }
+            (Method::POST, "/reset") => {
+                self.reset_scenarios().await;
+                self.clear_requests().await;
+                Response::empty().status(Status::NO_CONTENT)
+            }
+            (
+                _,
+                "/routes" | "/requests" | "/requests/find" | "/scenarios" | "/scenarios/reset"
+                | "/reset",
+            ) => error(Status::NOT_ALLOWED, "Method not allowed"),
+            _ => error(Status::NOT_FOUND, "Unknown admin endpoint"),
+        };
+        info!("Admin {} {}: {}", req.method, req.target(), response.status);
+        response
+    }
+
+    fn list_routes(&self) -> Response {
+        let routes = self.routes.borrow();
+        json_response(
+            Status::OK,
+            Value::from(routes.iter().map(route_json).collect::<Vec<_>>()),
+        )
+    }
+
+    /// Takes one or a list of the entries of `[[routes]]` in a config file. Body files are
+    /// refused, so clients cannot have the server answer with its local files.
+    async fn add_routes(&self, req: &Request) -> Response {
+        let entries = match serde_json::from_slice::<Value>(&req.body) {
+            Ok(Value::Array(entries)) => {
+                serde_json::from_value::<Vec<RouteEntry>>(Value::Array(entries))
+            }
+            Ok(entry) => serde_json::from_value::<RouteEntry>(entry).map(|entry| vec![entry]),
+            Err(err) => Err(err),
+        };
+        let entries = match entries {
+            Ok(entries) => entries,
+            Err(err) => return error(Status::BAD_REQUEST, &err.to_string()),
+        };
+        let config = RouteConfig { routes: entries };
+        if !config.body_files().is_empty() {
+            return error(
+                Status::BAD_REQUEST,
+                "body_file is not accepted through the admin API, send the body instead",
+            );
+        }
+        let added = match config.into_routes(Path::new(""), &self.defaults) {
+            Ok(added) => added,
+            Err(err) => return error(Status::BAD_REQUEST, &err.to_string()),
+        };
+        let described = added.iter().map(route_json).collect::<Vec<_>>();
+        let names = added.iter().map(Route::to_string).collect::<Vec<_>>();
+        // Changed in place so concurrent admin requests and reloads do not undo each other
+        self.routes
+            .send_modify(|table| Arc::make_mut(table).extend(added));
+        self.notify(format!("Admin added: {}", names.join(" ")))
+            .await;
+        json_response(Status::CREATED, Value::from(described))
+    }
+
+    /// Removes the routes on the `path` query parameter, only those of `method` when given
+    async fn remove_routes(&self, req: &Request) -> Response {
+        let query = req.query_params();
+        let param = |name: &str| {
+            query
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.as_str())
+        };
+        let Some(path) = param("path") else {
+            return error(Status::BAD_REQUEST, "The path query parameter is required");
+        };
+        let method =
+            match param("method").map(|method| method.to_ascii_uppercase().parse::<Method>()) {
+                Some(Ok(method)) => Some(method),
+                Some(Err(err)) => return error(Status::BAD_REQUEST, &err.to_string()),
+                None => None,
+            };
+
+        let mut removed = vec![];
+        self.routes.send_if_modified(|table| {
+            removed = Arc::make_mut(table)
+                .remove(|route| route.path == path && (method.is_none() || route.method == method));
+            !removed.is_empty()
+        });
+        if removed.is_empty() {
+            return error(Status::NOT_FOUND, "No route on that path");
+        }
+        let names = removed.iter().map(Route::to_string).collect::<Vec<_>>();
+        self.notify(format!("Admin removed: {}", names.join(" ")))
+            .await;
+        json_response(
+            Status::OK,
+            Value::from(removed.iter().map(route_json).collect::<Vec<_>>()),
+        )
+    }
+
+    /// Answers with the recorded requests matching the posted [`RequestPattern`] and their count
+    fn find_requests(&self, req: &Request) -> Response {
+        let pattern = match serde_json::from_slice::<RequestPattern>(&req.body) {
+            Ok(pattern) => pattern.compile(),
+            Err(err) => Err(err.to_string()),
+        };
+        let pattern = match pattern {
+            Ok(pattern) => pattern,
+            Err(err) => return error(Status::BAD_REQUEST, &err),
+        };
+        let found = self.journal.find(&pattern);
+        json_response(
+            Status::OK,
+            json!({
+                "count": found.len(),
+                "requests": found.iter().map(|exchange| exchange.to_json()).collect::<Vec<_>>(),
+            }),
+        )
+    }
+
+    fn list_scenarios(&self) -> Response {
+        let states = self
+            .routes
+            .borrow()
+            .scenario_states()
+            .into_iter()
+            .map(|(scenario, state)| (scenario, Value::from(state)))
+            .collect::<serde_json::Map<_, _>>();
+        json_response(Status::OK, Value::Object(states))
+    }
+
+    async fn reset_scenarios(&self) {
+        self.routes.borrow().reset();
+        self.notify("Admin reset scenarios and sequences".to_string())
+            .await;
+    }
+
+    async fn clear_requests(&self) {
+        self.journal.clear();
+        let _ = self.messages.send(Message::HistoryCleared).await;
+    }
+
+    async fn notify(&self, notice: String) {
+        let _ = self.messages.send(Message::Notice(notice)).await;
+    }
+}
+
+fn route_json(route: &Route) -> Value {
+    json!({
+        "route": route.to_string(),
+        "method": route.method,
+        "path": route.path,
+        "query": route.query,
+        "status": route.response.status,
+    })
+}
+
+fn json_response(status: Status, body: Value) -> Response {
+    Response::content(
+        serde_json::to_string_pretty(&body).unwrap_or_default(),
+        "application/json",
+    )
+    .status(status)
+}
+
+fn error(status: Status, message: &str) -> Response {
+    json_response(status, json!({ "error": message }))
+}
@@ -0,0 +1,110 @@
+use clap::Args;
+use rand::Rng;
+use serde::Deserialize;
+use std::{fmt::Display, time::Duration};
+
+/// Ways a response can be broken on purpose
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Fault {
+    /// Answer 500 in place of the response
+    Error,
+    /// Close the connection without answering
+    Close,
+    /// Send half of the response, then reset the connection
+    Reset,
+    /// Send bytes that are not HTTP
+    Malformed,
+}
+
+impl Display for Fault {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Fault::Error => write!(f, "error"),
+            Fault::Close => write!(f, "close"),
+            Fault::Reset => write!(f, "reset"),
+            Fault::Malformed => write!(f, "malformed"),
+        }
+    }
+}
+
+/// Latency and faults applied to responses, globally from the cli or per route
+#[derive(Args, Debug, Clone, Default, Deserialize)]
+#[command(about = None, long_about = None)]
+#[serde(deny_unknown_fields)]
+pub struct Chaos {
+    /// Milliseconds to wait before responding
+    #[arg(long)]
+    pub delay: Option<u64>,
+    /// Up to this many milliseconds added to the delay at random
+    #[arg(long)]
+    pub jitter: Option<u64>,
+    /// Bytes per second responses are written at
+    #[arg(long)]
+    pub bandwidth: Option<u64>,
+    /// Fault injected into responses
+    #[arg(long, value_enum)]
+    pub fault: Option<Fault>,
+    /// Percentage of requests the fault is injected into [default: 100]
+    #[arg(long)]
+    pub fault_rate: Option<f64>,
+}
+
+/// What was decided for one response
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Injection {
+    pub delay: Option<Duration>,
+    pub bandwidth: Option<u64>,
+    pub fault: Option<Fault>,
+}
+
+impl Chaos {
+    /// Fills the settings missing here from `fallback`
+    pub fn or(&self, fallback: &Chaos) -> Chaos {
+        Chaos {
+            delay: self.delay.or(fallback.delay),
+            jitter: self.jitter.or(fallback.jitter),
+            bandwidth: self.bandwidth.or(fallback.bandwidth),
+            fault: self.fault.or(fallback.fault),
+            fault_rate: self.fault_rate.or(fallback.fault_rate),
+        }
+    }
+
+    /// Rolls the jitter and the fault rate for one response
+    pub fn inject(&self) -> Injection {
+        let mut rng = rand::thread_rng();
+        let jitter = match self.jitter {
+            Some(jitter) if jitter > 0 => rng.gen_range(0..=jitter),
+            _ => 0,
+        };
+        let delay = self.delay.unwrap_or(0) + jitter;
+        let rate = self.fault_rate.unwrap_or(100.0).clamp(0.0, 100.0);
+        Injection {
+            delay: (delay > 0).then(|| Duration::from_millis(delay)),
+            bandwidth: self.bandwidth.filter(|bandwidth| *bandwidth > 0),
+            fault: self.fault.filter(|_| rng.gen_range(0.0..100.0) < rate),
+        }
+    }
+}
+
+impl Injection {
+    pub fn is_empty(&self) -> bool {
+        *self == Injection::default()
+    }
+}
+
+impl Display for Injection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(delay) = self.delay {
+            parts.push(format!("delay {}ms", delay.as_millis()));
+        }
+        if let Some(bandwidth) = self.bandwidth {
+            parts.push(format!("{bandwidth} B/s"));
+        }
+        if let Some(fault) = self.fault {
+            parts.push(format!("fault {fault}"));
+        }
+        write!(f, "{}", parts.join(", "))
+    }
+}
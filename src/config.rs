@@ -0,0 +1,405 @@
+use crate::chaos::Chaos;
+use crate::http::{Headers, Method, Status};
+use crate::matcher::{json_pointer, Condition, Matcher, Predicate, Subject};
+use crate::resource::Resource;
+use crate::routes::{Route, RouteTable};
+use crate::scenario::{ScenarioStep, Sequence, SequenceMode};
+use crate::{EndpointContent, EndpointMeta, ResponseFormat};
+use indexmap::IndexMap;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{
+    error::Error,
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Route definitions read from a `--config` file
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RouteConfig {
+    #[serde(default)]
+    pub routes: Vec<RouteEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RouteEntry {
+    /// Path pattern, `:name` captures a segment and `*name` the rest of the path
+    pub path: String,
+    /// Query parameters the request must carry, `*` accepts any value
+    #[serde(default)]
+    pub query: IndexMap<String, String>,
+    #[serde(default, deserialize_with = "deserialize_method")]
+    pub method: Option<Method>,
+    #[serde(default = "default_status")]
+    pub status: u16,
+    #[serde(default)]
+    pub headers: IndexMap<String, String>,
+    /// Inline response body
+    pub body: Option<String>,
+    /// Response body read from a file, relative to the config file
+    pub body_file: Option<PathBuf>,
+    /// `json`, `html` or a full media type
+    pub content_type: Option<String>,
+    /// Render `{{...}}` expressions in the body and headers from the request
+    pub template: Option<bool>,
+    /// Latency and faults, unset settings are taken from the command line
+    #[serde(default)]
+    pub chaos: Chaos,
+    /// Serve an in-memory collection on the path, seeded from the body
+    #[serde(default)]
+    pub crud: bool,
+    /// Alternative responses tried in order, the route's own response is the fallback
+    #[serde(default)]
+    pub matchers: Vec<MatcherEntry>,
+    /// Responses served one per request in place of the route's own
+    #[serde(default)]
+    pub sequence: Vec<StepEntry>,
+    #[serde(default)]
+    pub sequence_mode: SequenceMode,
+    /// Scenario the route takes part in
+    pub scenario: Option<String>,
+    /// The route only answers while the scenario is in this state
+    pub required_state: Option<String>,
+    /// State the scenario moves to once the route answered
+    pub new_state: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StepEntry {
+    #[serde(default = "default_status")]
+    pub status: u16,
+    #[serde(default)]
+    pub headers: IndexMap<String, String>,
+    pub body: Option<String>,
+    pub body_file: Option<PathBuf>,
+    pub content_type: Option<String>,
+    pub template: Option<bool>,
+    /// Latency and faults, unset settings are taken from the route
+    #[serde(default)]
+    pub chaos: Chaos,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MatcherEntry {
+    /// Conditions that must all hold for this response to be picked
+    #[serde(default)]
+    pub when: Vec<ConditionEntry>,
+    #[serde(default = "default_status")]
+    pub status: u16,
+    #[serde(default)]
+    pub headers: IndexMap<String, String>,
+    pub body: Option<String>,
+    pub body_file: Option<PathBuf>,
+    pub content_type: Option<String>,
+    pub template: Option<bool>,
+    /// Latency and faults, unset settings are taken from the route
+    #[serde(default)]
+    pub chaos: Chaos,
+}
+
+/// Looks at one of `header`, `query` or `json` (a JSON path into the body), or at the whole
+/// body when none is given, and checks it with one of `equals`, `regex` or `present`
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConditionEntry {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub header: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub query: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub json: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub equals: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub regex: Option<String>,
+    /// Defaults to `true` when no other check is given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub present: Option<bool>,
+}
+
+fn default_status() -> u16 {
+    Status::OK.code()
+}
+
+fn deserialize_method<'de, D>(deserializer: D) -> Result<Option<Method>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let method: Option<String> = Option::deserialize(deserializer)?;
+    method
+        .map(|method| method.to_ascii_uppercase().parse::<Method>())
+        .transpose()
+        .map_err(serde::de::Error::custom)
+}
+
+#[derive(Debug)]
+pub struct ConfigError {
+    path: PathBuf,
+    error: String,
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.error)
+    }
+}
+
+impl Error for ConfigError {}
+
+impl RouteConfig {
+    /// Parses a config file, the format is picked from its extension
+    pub fn from_file(path: &Path) -> Result<RouteConfig, ConfigError> {
+        let error = |error: String| ConfigError {
+            path: path.to_path_buf(),
+            error,
+        };
+        let text = fs::read_to_string(path).map_err(|e| error(e.to_string()))?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => toml::from_str(&text).map_err(|e| error(e.to_string())),
+            Some("json") => serde_json::from_str(&text).map_err(|e| error(e.to_string())),
+            Some("yaml" | "yml") => serde_yaml::from_str(&text).map_err(|e| error(e.to_string())),
+            _ => Err(error(
+                "Unknown config format, valid extensions are [toml, json, yaml, yml]".to_string(),
+            )),
+        }
+    }
+
+    /// Body files the routes, their matchers and sequence steps read, as written in the config
+    pub fn body_files(&self) -> Vec<&Path> {
+        self.routes
+            .iter()
+            .flat_map(|route| {
+                route
+                    .matchers
+                    .iter()
+                    .map(|matcher| &matcher.body_file)
+                    .chain(route.sequence.iter().map(|step| &step.body_file))
+                    .chain([&route.body_file])
+            })
+            .flatten()
+            .map(PathBuf::as_path)
+            .collect()
+    }
+
+    /// Builds the route table, resolving body files against `base`. Only the templating of
+    /// `defaults` applies, and only to routes that leave it unset.
+    pub fn into_routes(
+        self,
+        base: &Path,
+        defaults: &EndpointMeta,
+    ) -> Result<RouteTable, ConfigError> {
+        self.routes
+            .into_iter()
+            .map(|entry| entry.into_routes(base, defaults))
+            .collect::<Result<Vec<_>, _>>()
+            .map(|routes| routes.into_iter().flatten().collect())
+    }
+}
+
+/// Reads the inline body or the body file of a route or matcher
+fn read_body(
+    route: &str,
+    body: Option<String>,
+    body_file: Option<&Path>,
+) -> Result<Option<String>, ConfigError> {
+    match (body, body_file) {
+        (Some(_), Some(file)) => Err(ConfigError {
+            path: file.to_path_buf(),
+            error: format!("Route {} sets both body and body_file", route),
+        }),
+        (Some(body), None) => Ok(Some(body)),
+        (None, Some(file)) => fs::read_to_string(file).map(Some).map_err(|e| ConfigError {
+            path: file.to_path_buf(),
+            error: e.to_string(),
+        }),
+        (None, None) => Ok(None),
+    }
+}
+
+fn endpoint_content(
+    content: Option<String>,
+    body_file: Option<&Path>,
+    content_type: Option<String>,
+    status: u16,
+    headers: &IndexMap<String, String>,
+    template: bool,
+) -> EndpointContent {
+    let format = match (content_type, body_file) {
+        (Some(content_type), _) => match content_type.parse::<ResponseFormat>() {
+            Ok(format) => format.to_string(),
+            Err(_) => content_type,
+        },
+        (None, Some(file)) => match file.extension().and_then(|ext| ext.to_str()) {
+            Some("html") => ResponseFormat::Html.to_string(),
+            _ => ResponseFormat::Json.to_string(),
+        },
+        (None, None) => ResponseFormat::Json.to_string(),
+    };
+
+    let mut response_headers = Headers::new();
+    headers
+        .iter()
+        .for_each(|(name, value)| response_headers.push(name, value));
+
+    EndpointContent {
+        content,
+        format,
+        status: Status(status),
+        headers: response_headers,
+        template,
+        ..Default::default()
+    }
+}
+
+impl RouteEntry {
+    fn into_routes(self, base: &Path, defaults: &EndpointMeta) -> Result<Vec<Route>, ConfigError> {
+        let body_file = self.body_file.map(|file| base.join(file));
+        let content = read_body(&self.path, self.body, body_file.as_deref())?;
+
+        if self.crud {
+            return Resource::new(&self.path, content.as_deref())
+                .map(Resource::into_routes)
+                .map_err(|e| ConfigError {
+                    path: body_file.unwrap_or_else(|| base.to_path_buf()),
+                    error: e.to_string(),
+                });
+        }
+
+        let matchers = self
+            .matchers
+            .into_iter()
+            .map(|matcher| matcher.into_matcher(&self.path, base, defaults, &self.chaos))
+            .collect::<Result<Vec<_>, _>>()?;
+        let steps = self
+            .sequence
+            .into_iter()
+            .map(|step| step.into_content(&self.path, base, defaults, &self.chaos))
+            .collect::<Result<Vec<_>, _>>()?;
+        let scenario = match (self.scenario, &self.required_state, &self.new_state) {
+            (Some(scenario), required_state, new_state) => Some(ScenarioStep {
+                scenario,
+                required_state: required_state.clone(),
+                new_state: new_state.clone(),
+            }),
+            (None, None, None) => None,
+            (None, _, _) => {
+                return Err(ConfigError {
+                    path: base.to_path_buf(),
+                    error: format!(
+                        "Route {} sets a scenario state without a scenario",
+                        self.path
+                    ),
+                })
+            }
+        };
+
+        let route = Route::new(
+            self.method,
+            &self.path,
+            endpoint_content(
+                content,
+                body_file.as_deref(),
+                self.content_type,
+                self.status,
+                &self.headers,
+                self.template.or(defaults.template).unwrap_or(false),
+            )
+            .with_chaos(self.chaos),
+        )
+        .with_query(self.query)
+        .with_matchers(matchers);
+        let route = match steps.is_empty() {
+            true => route,
+            false => route.with_sequence(Sequence::new(steps, self.sequence_mode)),
+        };
+        Ok(vec![match scenario {
+            Some(scenario) => route.with_scenario(scenario),
+            None => route,
+        }])
+    }
+}
+
+impl StepEntry {
+    fn into_content(
+        self,
+        route: &str,
+        base: &Path,
+        defaults: &EndpointMeta,
+        chaos: &Chaos,
+    ) -> Result<EndpointContent, ConfigError> {
+        let body_file = self.body_file.map(|file| base.join(file));
+        let content = read_body(route, self.body, body_file.as_deref())?;
+        Ok(endpoint_content(
+            content,
+            body_file.as_deref(),
+            self.content_type,
+            self.status,
+            &self.headers,
+            self.template.or(defaults.template).unwrap_or(false),
+        )
+        .with_chaos(self.chaos.or(chaos)))
+    }
+}
+
+impl MatcherEntry {
+    fn into_matcher(
+        self,
+        route: &str,
+        base: &Path,
+        defaults: &EndpointMeta,
+        chaos: &Chaos,
+    ) -> Result<Matcher, ConfigError> {
+        let body_file = self.body_file.map(|file| base.join(file));
+        let content = read_body(route, self.body, body_file.as_deref())?;
+        let conditions = self
+            .when
+            .into_iter()
+            .map(ConditionEntry::into_condition)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|error| ConfigError {
+                path: base.to_path_buf(),
+                error: format!("Route {route}: {error}"),
+            })?;
+        Ok(Matcher {
+            conditions,
+            response: endpoint_content(
+                content,
+                body_file.as_deref(),
+                self.content_type,
+                self.status,
+                &self.headers,
+                self.template.or(defaults.template).unwrap_or(false),
+            )
+            .with_chaos(self.chaos.or(chaos)),
+        })
+    }
+}
+
+impl ConditionEntry {
+    pub fn into_condition(self) -> Result<Condition, String> {
+        let subject = match (self.header, self.query, self.json) {
+            (Some(header), None, None) => Subject::Header(header),
+            (None, Some(query), None) => Subject::Query(query),
+            (None, None, Some(path)) => {
+                json_pointer(&path)?;
+                Subject::Json(path)
+            }
+            (None, None, None) => Subject::Body,
+            _ => return Err("a condition looks at one of header, query or json".to_string()),
+        };
+        let predicate = match (self.equals, self.regex, self.present) {
+            (Some(expected), None, None) => Predicate::Equals(expected),
+            (None, Some(regex), None) => {
+                Predicate::Regex(Regex::new(&regex).map_err(|e| e.to_string())?)
+            }
+            (None, None, present) => Predicate::Present(present.unwrap_or(true)),
+            _ => return Err("a condition checks one of equals, regex or present".to_string()),
+        };
+        Ok(Condition { subject, predicate })
+    }
+}
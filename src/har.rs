@@ -0,0 +1,101 @@
+use crate::http::Headers;
+use crate::journal::Exchange;
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use std::{fs, path::Path};
+
+/// HAR file 'e' in the TUI writes to without --har-out
+pub const DEFAULT_HAR: &str = "./testsuite.har";
+
+/// HTTP Archive 1.2 log of `exchanges`, as read by browser devtools
+pub fn to_har(exchanges: &[Exchange]) -> Value {
+    json!({
+        "log": {
+            "version": "1.2",
+            "creator": {
+                "name": env!("CARGO_PKG_NAME"),
+                "version": env!("CARGO_PKG_VERSION"),
+            },
+            "pages": [],
+            "entries": exchanges.iter().map(entry).collect::<Vec<_>>(),
+        }
+    })
+}
+
+/// Writes `exchanges` to `path` as a HAR file
+pub fn write_har(path: &Path, exchanges: &[Exchange]) -> Result<()> {
+    let har = serde_json::to_string_pretty(&to_har(exchanges))?;
+    fs::write(path, har).with_context(|| format!("Could not write {}", path.display()))
+}
+
+fn entry(exchange: &Exchange) -> Value {
+    let request = &exchange.request;
+    let response = &exchange.response;
+    let scheme = match exchange.secure {
+        true => "https",
+        false => "http",
+    };
+    let host = request
+        .headers
+        .get("Host")
+        .map(str::to_string)
+        .unwrap_or_else(|| exchange.addr.to_string());
+    let time = exchange.elapsed.as_secs_f64() * 1000.0;
+
+    let mut har_request = json!({
+        "method": request.method,
+        "url": format!("{scheme}://{host}{}", request.target()),
+        "httpVersion": request.version,
+        "cookies": [],
+        "headers": headers(&request.headers),
+        "queryString": request
+            .query_params()
+            .into_iter()
+            .map(|(name, value)| json!({ "name": name, "value": value }))
+            .collect::<Vec<_>>(),
+        "headersSize": -1,
+        "bodySize": request.body.len(),
+    });
+    if !request.body.is_empty() {
+        har_request["postData"] = json!({
+            "mimeType": request.headers.get("Content-Type").unwrap_or_default(),
+            "text": request.body_text(),
+        });
+    }
+
+    json!({
+        "startedDateTime": exchange.received.to_rfc3339(),
+        "time": time,
+        "request": har_request,
+        "response": {
+            "status": response.status.code(),
+            "statusText": response.status.reason(),
+            "httpVersion": response.version,
+            "cookies": [],
+            "headers": headers(&response.headers),
+            "content": {
+                "size": response.body.len(),
+                "mimeType": response.headers.get("Content-Type").unwrap_or_default(),
+                "text": response.body_text(),
+            },
+            "redirectURL": response.headers.get("Location").unwrap_or_default(),
+            "headersSize": -1,
+            "bodySize": response.body.len(),
+        },
+        "cache": {},
+        "timings": {
+            "send": 0,
+            "wait": time,
+            "receive": 0,
+        },
+        "connection": exchange.connection.to_string(),
+        "_clientAddress": exchange.addr.to_string(),
+    })
+}
+
+fn headers(headers: &Headers) -> Vec<Value> {
+    headers
+        .iter()
+        .map(|(name, value)| json!({ "name": name, "value": value }))
+        .collect()
+}
@@ -0,0 +1,73 @@
+use crate::journal::Exchange;
+use crate::{ConnectionFailedError, Message, ResponseMessage};
+use clap::Args;
+
+/// How exchanges are printed without the TUI
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Default, PartialEq)]
+pub enum OutputFormat {
+    /// One readable line per exchange
+    #[default]
+    Human,
+    /// One JSON object per line, like the entries of `GET /__admin/requests`
+    Json,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct Headless {
+    /// Run without the TUI, printing every exchange to stdout
+    #[arg(long, default_value_t = false)]
+    pub headless: bool,
+    /// How exchanges are printed in headless mode
+    #[arg(long, default_value_t, value_enum, requires = "headless")]
+    pub output: OutputFormat,
+    /// Stop after serving this many requests
+    #[arg(long, requires = "headless")]
+    pub exit_after: Option<usize>,
+    /// Stop after running for this many seconds
+    #[arg(long, requires = "headless")]
+    pub exit_timeout: Option<u64>,
+}
+
+impl OutputFormat {
+    /// Line printed to stdout for an exchange, `served` counts the exchanges from 1
+    pub fn exchange(&self, message: &ResponseMessage, served: u64) -> String {
+        match self {
+            OutputFormat::Human => {
+                let mut line = format!(
+                    "{} {} #{} {} {} -> {} ({} B)",
+                    message.received.to_rfc3339(),
+                    message.addr,
+                    message.connection,
+                    message.request.method,
+                    message.request.target(),
+                    message.response.status,
+                    message.response.body.len()
+                );
+                if let Some(matcher) = &message.matcher {
+                    line.push_str(&format!(" [{matcher}]"));
+                }
+                if let Some(injected) = &message.injected {
+                    line.push_str(&format!(" [{injected}]"));
+                }
+                line
+            }
+            OutputFormat::Json => Exchange::new(served, message).to_json().to_string(),
+        }
+    }
+}
+
+/// Line printed to stderr for server events other than exchanges
+pub fn event(message: &Message) -> Option<String> {
+    match message {
+        Message::ConnectionFailed(ConnectionFailedError::Connection(err)) => {
+            Some(format!("Connection failed: {err}"))
+        }
+        Message::ConnectionFailed(ConnectionFailedError::Parsing((addr, err))) => {
+            Some(format!("{addr}: {err}"))
+        }
+        Message::RoutesReloaded(reloaded) => Some(reloaded.to_string()),
+        Message::Notice(notice) => Some(notice.clone()),
+        Message::HistoryCleared => Some("Admin cleared the request history".to_string()),
+        Message::ConnectionReceived(..) | Message::Response(_) => None,
+    }
+}
@@ -0,0 +1,778 @@
+use serde::Serialize;
+use std::{borrow::Cow, error::Error, fmt::Display, io, str::FromStr};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Upper bound for the request line and headers combined
+pub const MAX_HEADER_SIZE: usize = 64 * 1024;
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Serialize)]
+pub enum Method {
+    GET,
+    HEAD,
+    POST,
+    PUT,
+    PATCH,
+    DELETE,
+    OPTIONS,
+    TRACE,
+    CONNECT,
+}
+
+impl Method {
+    /// Every supported method, in the order they are listed in `Allow` headers
+    pub const ALL: [Method; 9] = [
+        Method::GET,
+        Method::HEAD,
+        Method::POST,
+        Method::PUT,
+        Method::PATCH,
+        Method::DELETE,
+        Method::OPTIONS,
+        Method::TRACE,
+        Method::CONNECT,
+    ];
+}
+
+impl FromStr for Method {
+    type Err = RequestError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "GET" => Ok(Method::GET),
+            "HEAD" => Ok(Method::HEAD),
+            "POST" => Ok(Method::POST),
+            "PUT" => Ok(Method::PUT),
+            "PATCH" => Ok(Method::PATCH),
+            "DELETE" => Ok(Method::DELETE),
+            "OPTIONS" => Ok(Method::OPTIONS),
+            "TRACE" => Ok(Method::TRACE),
+            "CONNECT" => Ok(Method::CONNECT),
+            _ => Err(RequestError::Malformed(format!("Unsupported method: {s}"))),
+        }
+    }
+}
+
+impl Display for Method {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// Numeric http status code
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize)]
+pub struct Status(pub u16);
+
+impl Status {
+    pub const OK: Status = Status(200);
+    pub const CREATED: Status = Status(201);
+    pub const NO_CONTENT: Status = Status(204);
+    pub const BAD_REQUEST: Status = Status(400);
+    pub const NOT_FOUND: Status = Status(404);
+    pub const NOT_ALLOWED: Status = Status(405);
+    pub const CONFLICT: Status = Status(409);
+    pub const PAYLOAD_TOO_LARGE: Status = Status(413);
+    pub const HEADERS_TOO_LARGE: Status = Status(431);
+    pub const INTERNAL_SERVER_ERROR: Status = Status(500);
+    pub const BAD_GATEWAY: Status = Status(502);
+
+    pub fn code(&self) -> u16 {
+        self.0
+    }
+
+    /// Canonical reason phrase, empty for unknown codes
+    pub fn reason(&self) -> &'static str {
+        match self.0 {
+            100 => "Continue",
+            101 => "Switching Protocols",
+            200 => "OK",
+            201 => "Created",
+            202 => "Accepted",
+            204 => "No Content",
+            206 => "Partial Content",
+            301 => "Moved Permanently",
+            302 => "Found",
+            303 => "See Other",
+            304 => "Not Modified",
+            307 => "Temporary Redirect",
+            308 => "Permanent Redirect",
+            400 => "Bad Request",
+            401 => "Unauthorized",
+            403 => "Forbidden",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            406 => "Not Acceptable",
+            408 => "Request Timeout",
+            409 => "Conflict",
+            410 => "Gone",
+            411 => "Length Required",
+            412 => "Precondition Failed",
+            413 => "Payload Too Large",
+            415 => "Unsupported Media Type",
+            422 => "Unprocessable Entity",
+            429 => "Too Many Requests",
+            431 => "Request Header Fields Too Large",
+            500 => "Internal Server Error",
+            501 => "Not Implemented",
+            502 => "Bad Gateway",
+            503 => "Service Unavailable",
+            504 => "Gateway Timeout",
+            _ => "",
+        }
+    }
+}
+
+impl Default for Status {
+    fn default() -> Self {
+        Status::OK
+    }
+}
+
+impl Display for Status {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.0, self.reason())
+    }
+}
+
+/// Ordered list of header fields, lookups are case insensitive
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct Headers(Vec<(String, String)>);
+
+impl Headers {
+    pub fn new() -> Self {
+        Headers(Vec::new())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    pub fn push(&mut self, name: &str, value: &str) {
+        self.0.push((name.to_string(), value.to_string()));
+    }
+
+    /// Replaces every field called `name` with a single value
+    pub fn set(&mut self, name: &str, value: &str) {
+        self.remove(name);
+        self.push(name, value);
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.0.retain(|(key, _)| !key.eq_ignore_ascii_case(name));
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Request {
+    pub method: Method,
+    /// Request target without the query string
+    pub path: String,
+    /// Raw query string, without the leading `?`
+    pub query: Option<String>,
+    pub version: String,
+    pub headers: Headers,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    pub fn body_text(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.body)
+    }
+
+    /// Decoded query parameters in the order they were sent
+    pub fn query_params(&self) -> Vec<(String, String)> {
+        self.query
+            .as_deref()
+            .unwrap_or_default()
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| match pair.split_once('=') {
+                Some((key, value)) => (percent_decode(key), percent_decode(value)),
+                None => (percent_decode(pair), String::new()),
+            })
+            .collect()
+    }
+
+    /// Path and query as sent in the request line
+    pub fn target(&self) -> String {
+        match &self.query {
+            Some(query) => format!("{}?{}", self.path, query),
+            None => self.path.clone(),
+        }
+    }
+}
+
+impl Display for Request {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} {}\r\n", self.method, self.target(), self.version)?;
+        for (name, value) in self.headers.iter() {
+            write!(f, "{name}: {value}\r\n")?;
+        }
+        write!(f, "\r\n{}", self.body_text())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    pub version: String,
+    pub status: Status,
+    pub headers: Headers,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    /// Creates a response with no body
+    pub fn empty() -> Self {
+        Response {
+            version: "HTTP/1.1".to_string(),
+            status: Status::OK,
+            headers: Headers::new(),
+            body: Vec::new(),
+        }
+    }
+
+    /// Creates a response with a body and sets its `Content-Type`
+    pub fn content(body: impl Into<Vec<u8>>, content_type: &str) -> Self {
+        let mut response = Response {
+            body: body.into(),
+            ..Response::empty()
+        };
+        if !content_type.is_empty() {
+            response.headers.push("Content-Type", content_type);
+        }
+        response
+    }
+
+    pub fn status(self, status: Status) -> Self {
+        Response { status, ..self }
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push(name, value);
+        self
+    }
+
+    pub fn body_text(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.body)
+    }
+
+    /// Drops the body but keeps the `Content-Length` it would have had, as HEAD requires
+    pub fn without_body(mut self) -> Self {
+        if !self.headers.contains("Content-Length") {
+            self.headers
+                .push("Content-Length", &self.body.len().to_string());
+        }
+        self.body.clear();
+        self
+    }
+
+    /// Status line and headers, `Content-Length` is added when missing
+    pub fn head(&self) -> String {
+        let mut head = format!("{} {}\r\n", self.version, self.status);
+        for (name, value) in self.headers.iter() {
+            head.push_str(&format!("{name}: {value}\r\n"));
+        }
+        if !self.headers.contains("Content-Length") {
+            head.push_str(&format!("Content-Length: {}\r\n", self.body.len()));
+        }
+        head.push_str("\r\n");
+        head
+    }
+
+    /// Serializes the response as it is sent over the wire
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.head().into_bytes();
+        bytes.extend_from_slice(&self.body);
+        bytes
+    }
+}
+
+impl Display for Response {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.head(), self.body_text())
+    }
+}
+
+#[derive(Debug)]
+pub enum RequestError {
+    Io(io::Error),
+    Malformed(String),
+    HeadersTooLarge,
+    BodyTooLarge(usize),
+}
+
+impl RequestError {
+    /// Status to answer with before dropping the connection, if the client is still listening
+    pub fn status(&self) -> Option<Status> {
+        match self {
+            RequestError::Io(_) => None,
+            RequestError::Malformed(_) => Some(Status::BAD_REQUEST),
+            RequestError::HeadersTooLarge => Some(Status::HEADERS_TOO_LARGE),
+            RequestError::BodyTooLarge(_) => Some(Status::PAYLOAD_TOO_LARGE),
+        }
+    }
+}
+
+impl Display for RequestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RequestError::Io(err) => write!(f, "{}", err),
+            RequestError::Malformed(reason) => write!(f, "Malformed request: {}", reason),
+            RequestError::HeadersTooLarge => {
+                write!(f, "Request headers exceed {} bytes", MAX_HEADER_SIZE)
+            }
+            RequestError::BodyTooLarge(limit) => {
+                write!(f, "Request body exceeds limit of {} bytes", limit)
+            }
+        }
+    }
+}
+
+impl Error for RequestError {}
+
+impl From<io::Error> for RequestError {
+    fn from(err: io::Error) -> Self {
+        RequestError::Io(err)
+    }
+}
+
+/// Reads one request off the stream, honouring `Content-Length` and chunked transfer encoding.
+/// Clients that sent `Expect: 100-continue` are told to go on through `writer` once the body
+/// they announced fits.
+///
+/// Returns `Ok(None)` when the peer closes the connection before sending anything.
+pub async fn read_request<R: AsyncBufRead + Unpin, W: AsyncWrite + Unpin>(
+    reader: &mut R,
+    writer: &mut W,
+    max_body_size: usize,
+) -> Result<Option<Request>, RequestError> {
+    let mut head_size = 0;
+    // Clients may send empty lines ahead of the request line
+    let request_line = loop {
+        match read_line(reader, &mut head_size).await? {
+            None => return Ok(None),
+            Some(line) if line.is_empty() => continue,
+            Some(line) => break line,
+        }
+    };
+
+    let mut parts = request_line.split_whitespace();
+    let (method, target, version) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(method), Some(target), Some(version)) if version.starts_with("HTTP/") => {
+            (method.parse::<Method>()?, target, version)
+        }
+        _ => {
+            return Err(RequestError::Malformed(format!(
+                "Invalid request line: {request_line}"
+            )))
+        }
+    };
+    let (path, query) = match target.split_once('?') {
+        Some((path, query)) => (path.to_string(), Some(query.to_string())),
+        None => (target.to_string(), None),
+    };
+
+    let headers = read_headers(reader, &mut head_size).await?;
+    let expects_continue = headers
+        .get("Expect")
+        .is_some_and(|expect| expect.eq_ignore_ascii_case("100-continue"));
+    if expects_continue && version != "HTTP/1.0" {
+        // An oversized body is refused before the client sends it
+        if !is_chunked(&headers) && content_length(&headers)? > max_body_size {
+            return Err(RequestError::BodyTooLarge(max_body_size));
+        }
+        writer.write_all(b"HTTP/1.1 100 Continue\r\n\r\n").await?;
+        writer.flush().await?;
+    }
+    let body = read_body(reader, &headers, max_body_size).await?;
+
+    Ok(Some(Request {
+        method,
+        path,
+        query,
+        version: version.to_string(),
+        headers,
+        body,
+    }))
+}
+
+/// Reads one response off the stream, as sent back by an upstream server.
+///
+/// Bodies without a length are read until the peer closes the connection. Interim `1xx`
+/// responses other than `101 Switching Protocols` are skipped.
+pub async fn read_response<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    method: Method,
+    max_body_size: usize,
+) -> Result<Response, RequestError> {
+    let (version, status, headers) = loop {
+        let mut head_size = 0;
+        let status_line = read_line(reader, &mut head_size).await?.ok_or_else(|| {
+            RequestError::Malformed("Connection closed before response".to_string())
+        })?;
+        let mut parts = status_line.splitn(3, ' ');
+        let (version, status) = match (parts.next(), parts.next().map(str::parse::<u16>)) {
+            (Some(version), Some(Ok(status))) if version.starts_with("HTTP/") => {
+                (version.to_string(), status)
+            }
+            _ => {
+                return Err(RequestError::Malformed(format!(
+                    "Invalid status line: {status_line}"
+                )))
+            }
+        };
+        let headers = read_headers(reader, &mut head_size).await?;
+        if !matches!(status, 100 | 102..=199) {
+            break (version, status, headers);
+        }
+    };
+    let body = match (method, status) {
+        (Method::HEAD, _) | (_, 100..=199 | 204 | 304) => Vec::new(),
+        _ if headers.contains("Transfer-Encoding") || headers.contains("Content-Length") => {
+            read_body(reader, &headers, max_body_size).await?
+        }
+        _ => {
+            let mut body = Vec::new();
+            (&mut *reader)
+                .take((max_body_size as u64).saturating_add(1))
+                .read_to_end(&mut body)
+                .await?;
+            if body.len() > max_body_size {
+                return Err(RequestError::BodyTooLarge(max_body_size));
+            }
+            body
+        }
+    };
+
+    Ok(Response {
+        version,
+        status: Status(status),
+        headers,
+        body,
+    })
+}
+
+async fn read_headers<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    head_size: &mut usize,
+) -> Result<Headers, RequestError> {
+    let mut headers = Headers::new();
+    loop {
+        let line = read_line(reader, head_size)
+            .await?
+            .ok_or_else(|| RequestError::Malformed("Connection closed in headers".to_string()))?;
+        if line.is_empty() {
+            break;
+        }
+        match line.split_once(':') {
+            Some((name, value)) => headers.push(name.trim(), value.trim()),
+            None => return Err(RequestError::Malformed(format!("Invalid header: {line}"))),
+        }
+    }
+    Ok(headers)
+}
+
+async fn read_body<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    headers: &Headers,
+    max_body_size: usize,
+) -> Result<Vec<u8>, RequestError> {
+    if is_chunked(headers) {
+        return read_chunked_body(reader, max_body_size).await;
+    }
+
+    let length = content_length(headers)?;
+    if length > max_body_size {
+        return Err(RequestError::BodyTooLarge(max_body_size));
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).await?;
+    Ok(body)
+}
+
+fn is_chunked(headers: &Headers) -> bool {
+    headers
+        .get("Transfer-Encoding")
+        .map(|encoding| encoding.to_ascii_lowercase().contains("chunked"))
+        .unwrap_or(false)
+}
+
+/// Body length announced by `Content-Length`, 0 without one
+fn content_length(headers: &Headers) -> Result<usize, RequestError> {
+    match headers.get("Content-Length") {
+        Some(length) => length
+            .parse::<usize>()
+            .map_err(|_| RequestError::Malformed(format!("Invalid Content-Length: {length}"))),
+        None => Ok(0),
+    }
+}
+
+async fn read_chunked_body<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    max_body_size: usize,
+) -> Result<Vec<u8>, RequestError> {
+    let mut body = Vec::new();
+    loop {
+        // Each chunk size line is held to the header limit on its own
+        let mut line_size = 0;
+        let line = read_line(reader, &mut line_size)
+            .await?
+            .ok_or_else(|| RequestError::Malformed("Connection closed in chunk".to_string()))?;
+        let size = line.split(';').next().unwrap_or_default().trim();
+        let size = usize::from_str_radix(size, 16)
+            .map_err(|_| RequestError::Malformed(format!("Invalid chunk size: {line}")))?;
+        if size == 0 {
+            // Trailers are discarded, together they are held to the header limit
+            let mut trailer_size = 0;
+            read_headers(reader, &mut trailer_size).await?;
+            return Ok(body);
+        }
+        let end = match body.len().checked_add(size) {
+            Some(end) if end <= max_body_size => end,
+            _ => return Err(RequestError::BodyTooLarge(max_body_size)),
+        };
+        let start = body.len();
+        body.resize(end, 0);
+        reader.read_exact(&mut body[start..]).await?;
+
+        let mut delimiter = [0; 2];
+        reader.read_exact(&mut delimiter).await?;
+        if &delimiter != b"\r\n" {
+            return Err(RequestError::Malformed(
+                "Missing chunk delimiter".to_string(),
+            ));
+        }
+    }
+}
+
+/// Reads a line without its line ending, `None` on a clean end of stream
+async fn read_line<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    total: &mut usize,
+) -> Result<Option<String>, RequestError> {
+    let mut line = Vec::new();
+    let remaining = MAX_HEADER_SIZE.saturating_sub(*total) as u64 + 1;
+    let read = (&mut *reader)
+        .take(remaining)
+        .read_until(b'\n', &mut line)
+        .await?;
+    *total += read;
+    if read == 0 {
+        return Ok(None);
+    }
+    if *total > MAX_HEADER_SIZE {
+        return Err(RequestError::HeadersTooLarge);
+    }
+    if line.last() != Some(&b'\n') {
+        return Err(RequestError::Malformed(
+            "Connection closed mid line".to_string(),
+        ));
+    }
+    while matches!(line.last(), Some(b'\n' | b'\r')) {
+        line.pop();
+    }
+    String::from_utf8(line)
+        .map(Some)
+        .map_err(|_| RequestError::Malformed("Request head is not valid UTF-8".to_string()))
+}
+
+pub(crate) fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'+' => decoded.push(b' '),
+            b'%' if index + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[index + 1..index + 3]).ok();
+                match hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                    Some(byte) => {
+                        decoded.push(byte);
+                        index += 2;
+                    }
+                    None => decoded.push(b'%'),
+                }
+            }
+            byte => decoded.push(byte),
+        }
+        index += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn read(input: &[u8], max_body_size: usize) -> Result<Option<Request>, RequestError> {
+        let mut reader = input;
+        read_request(&mut reader, &mut Vec::new(), max_body_size).await
+    }
+
+    #[tokio::test]
+    async fn reads_content_length_body() {
+        let request = read(
+            b"POST /users?page=2 HTTP/1.1\r\nHost: a\r\nContent-Length: 5\r\n\r\nhello",
+            1024,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(request.method, Method::POST);
+        assert_eq!(request.path, "/users");
+        assert_eq!(request.query.as_deref(), Some("page=2"));
+        assert_eq!(request.headers.get("host"), Some("a"));
+        assert_eq!(request.body, b"hello");
+    }
+
+    #[tokio::test]
+    async fn reads_chunked_body_and_skips_trailers() {
+        let request = read(
+            b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n\
+              5;ext=1\r\nhello\r\n6\r\n world\r\n0\r\nTrailer: x\r\n\r\n",
+            1024,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(request.body, b"hello world");
+    }
+
+    #[tokio::test]
+    async fn rejects_oversized_bodies() {
+        let result = read(
+            b"POST / HTTP/1.1\r\nContent-Length: 11\r\n\r\nhello world",
+            10,
+        )
+        .await;
+        assert!(matches!(result, Err(RequestError::BodyTooLarge(10))));
+
+        let result = read(
+            b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n6\r\nhello \r\n5\r\nworld\r\n0\r\n\r\n",
+            10,
+        )
+        .await;
+        assert!(matches!(result, Err(RequestError::BodyTooLarge(10))));
+    }
+
+    #[tokio::test]
+    async fn reads_many_small_chunks_past_the_header_limit() {
+        let mut input = b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n".to_vec();
+        for _ in 0..30_000 {
+            input.extend(b"a\r\n0123456789\r\n");
+        }
+        input.extend(b"0\r\n\r\n");
+        let request = read(&input, 16 * 1024 * 1024).await.unwrap().unwrap();
+        assert_eq!(request.body.len(), 300_000);
+    }
+
+    #[tokio::test]
+    async fn rejects_chunk_sizes_that_overflow() {
+        let result = read(
+            b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\nffffffffffffffff\r\n",
+            1024,
+        )
+        .await;
+        assert!(matches!(result, Err(RequestError::BodyTooLarge(1024))));
+    }
+
+    #[tokio::test]
+    async fn rejects_headers_over_the_limit() {
+        let mut input = b"GET / HTTP/1.1\r\nX-Large: ".to_vec();
+        input.extend(vec![b'a'; MAX_HEADER_SIZE]);
+        input.extend(b"\r\n\r\n");
+        let result = read(&input, 1024).await;
+        assert!(matches!(result, Err(RequestError::HeadersTooLarge)));
+    }
+
+    #[tokio::test]
+    async fn rejects_malformed_request_lines() {
+        let result = read(b"GET /\r\n\r\n", 1024).await;
+        assert!(matches!(result, Err(RequestError::Malformed(_))));
+    }
+
+    #[tokio::test]
+    async fn reads_pipelined_requests_in_order() {
+        let mut reader: &[u8] = b"GET /first HTTP/1.1\r\n\r\n\
+            POST /second HTTP/1.1\r\nContent-Length: 2\r\n\r\nok\
+            GET /third HTTP/1.1\r\n\r\n";
+        let mut paths = vec![];
+        while let Some(request) = read_request(&mut reader, &mut Vec::new(), 1024)
+            .await
+            .unwrap()
+        {
+            paths.push(request.path);
+        }
+        assert_eq!(paths, ["/first", "/second", "/third"]);
+    }
+
+    #[tokio::test]
+    async fn answers_expect_continue_when_the_body_fits() {
+        let mut reader: &[u8] =
+            b"POST / HTTP/1.1\r\nExpect: 100-continue\r\nContent-Length: 2\r\n\r\nok";
+        let mut interim = Vec::new();
+        let request = read_request(&mut reader, &mut interim, 1024)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(interim, b"HTTP/1.1 100 Continue\r\n\r\n");
+        assert_eq!(request.body, b"ok");
+
+        let mut reader: &[u8] =
+            b"POST / HTTP/1.1\r\nExpect: 100-continue\r\nContent-Length: 2048\r\n\r\n";
+        let mut interim = Vec::new();
+        let result = read_request(&mut reader, &mut interim, 1024).await;
+        assert!(matches!(result, Err(RequestError::BodyTooLarge(1024))));
+        assert!(interim.is_empty());
+    }
+
+    #[tokio::test]
+    async fn reads_responses_without_a_length_until_close() {
+        let mut reader: &[u8] = b"HTTP/1.0 200 OK\r\nContent-Type: text/plain\r\n\r\nall of it";
+        let response = read_response(&mut reader, Method::GET, 1024).await.unwrap();
+        assert_eq!(response.status, Status::OK);
+        assert_eq!(response.body, b"all of it");
+    }
+
+    #[tokio::test]
+    async fn skips_interim_responses() {
+        let mut reader: &[u8] = b"HTTP/1.1 100 Continue\r\n\r\n\
+            HTTP/1.1 102 Processing\r\n\r\n\
+            HTTP/1.1 201 Created\r\nContent-Length: 2\r\n\r\nok";
+        let response = read_response(&mut reader, Method::POST, 1024)
+            .await
+            .unwrap();
+        assert_eq!(response.status, Status(201));
+        assert_eq!(response.body, b"ok");
+    }
+
+    #[tokio::test]
+    async fn reads_responses_without_a_length_under_any_limit() {
+        let mut reader: &[u8] = b"HTTP/1.0 200 OK\r\n\r\nbody";
+        let response = read_response(&mut reader, Method::GET, usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(response.body, b"body");
+    }
+
+    #[test]
+    fn decodes_percent_escapes() {
+        assert_eq!(percent_decode("a%20b+c%2Fd%zz"), "a b c/d%zz");
+    }
+}
@@ -0,0 +1,147 @@
+use crate::http::{Headers, Request, Response};
+use crate::verify::CompiledPattern;
+use crate::{ConnectionId, ResponseMessage};
+use chrono::{DateTime, Utc};
+use serde_json::{json, Map, Value};
+use std::{
+    collections::VecDeque,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+/// One request the server answered, with the response it got
+#[derive(Debug, Clone)]
+pub struct Exchange {
+    /// Position in the journal, counting from 1 and not reused after a clear
+    pub id: u64,
+    pub connection: ConnectionId,
+    pub addr: SocketAddr,
+    pub received: DateTime<Utc>,
+    pub request: Request,
+    pub response: Response,
+    pub matcher: Option<String>,
+    pub injected: Option<String>,
+    pub secure: bool,
+    pub elapsed: Duration,
+}
+
+/// The last exchanges served since startup or the last clear, shared with the admin API so they
+/// can be read outside the TUI
+#[derive(Debug)]
+pub struct Journal {
+    exchanges: Mutex<VecDeque<Exchange>>,
+    next: AtomicU64,
+    /// Exchanges kept, older ones are dropped as new ones come in
+    limit: usize,
+}
+
+impl Journal {
+    pub fn new(limit: usize) -> Self {
+        Journal {
+            exchanges: Mutex::new(VecDeque::new()),
+            next: AtomicU64::new(0),
+            limit,
+        }
+    }
+
+    pub fn record(&self, message: &ResponseMessage) {
+        let exchange = Exchange::new(self.next.fetch_add(1, Ordering::SeqCst) + 1, message);
+        let mut exchanges = self.exchanges.lock().expect("journal lock is not poisoned");
+        exchanges.push_back(exchange);
+        while exchanges.len() > self.limit {
+            exchanges.pop_front();
+        }
+    }
+
+    /// Every recorded exchange, oldest first
+    pub fn exchanges(&self) -> Vec<Exchange> {
+        self.exchanges
+            .lock()
+            .expect("journal lock is not poisoned")
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    /// Recorded exchanges whose request matches `pattern`, oldest first
+    pub fn find(&self, pattern: &CompiledPattern) -> Vec<Exchange> {
+        self.exchanges
+            .lock()
+            .expect("journal lock is not poisoned")
+            .iter()
+            .filter(|exchange| pattern.matches(&exchange.request))
+            .cloned()
+            .collect()
+    }
+
+    pub fn clear(&self) {
+        self.exchanges
+            .lock()
+            .expect("journal lock is not poisoned")
+            .clear();
+    }
+}
+
+/// Headers as a JSON object, repeated headers joined with `, `
+pub fn headers_json(headers: &Headers) -> Value {
+    let mut object = Map::new();
+    for (name, value) in headers.iter() {
+        match object.get_mut(name) {
+            Some(Value::String(existing)) => {
+                existing.push_str(", ");
+                existing.push_str(value);
+            }
+            _ => {
+                object.insert(name.to_string(), Value::from(value));
+            }
+        }
+    }
+    Value::Object(object)
+}
+
+impl Exchange {
+    pub fn new(id: u64, message: &ResponseMessage) -> Self {
+        Exchange {
+            id,
+            connection: message.connection,
+            addr: message.addr,
+            received: message.received,
+            request: message.request.clone(),
+            response: message.response.clone(),
+            matcher: message.matcher.clone(),
+            injected: message.injected.clone(),
+            secure: message.secure,
+            elapsed: message.elapsed,
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "id": self.id,
+            "connection": self.connection,
+            "address": self.addr.to_string(),
+            "received": self.received.to_rfc3339(),
+            "request": {
+                "method": self.request.method,
+                "path": self.request.path,
+                "query": self.request.query,
+                "version": self.request.version,
+                "headers": headers_json(&self.request.headers),
+                "body": self.request.body_text(),
+            },
+            "response": {
+                "status": self.response.status,
+                "headers": headers_json(&self.response.headers),
+                "body": self.response.body_text(),
+            },
+            "matcher": self.matcher,
+            "injected": self.injected,
+            "secure": self.secure,
+            "elapsed_ms": self.elapsed.as_secs_f64() * 1000.0,
+        })
+    }
+}
@@ -1,11 +1,38 @@
-use clap::{Args, Parser};
+use clap::{Args, Parser, Subcommand};
 use log::{warn, info};
 use log::LevelFilter;
-use anyhow::Result;
-use nanohttp::{Method, Response, Status};
-use serde::Serialize;
+use anyhow::{anyhow, Result};
+use chaos::Chaos;
+use config::RouteConfig;
+use chrono::{DateTime, Utc};
+use http::{Headers, Method, Request, Response, Status};
+use resource::Resource;
+use proxy::Upstream;
+use routes::{Route, RouteTable};
+use template::TemplateContext;
+use tls::{Tls, TlsInfo};
+use headless::Headless;
+use serde::{Deserialize, Serialize};
 use indexmap::IndexMap;
-use std::{error::Error, fmt::Display, fs, path::PathBuf, str::FromStr, net::SocketAddr};
+use std::{error::Error, fmt::Display, fs, path::{Path, PathBuf}, str::FromStr, net::SocketAddr, time::Duration};
+
+pub mod admin;
+pub mod chaos;
+pub mod config;
+pub mod har;
+pub mod headless;
+pub mod http;
+pub mod journal;
+pub mod matcher;
+pub mod proxy;
+pub mod reload;
+pub mod resource;
+pub mod routes;
+pub mod scenario;
+pub mod session;
+pub mod template;
+pub mod tls;
+pub mod verify;
 
 #[derive(Clone, clap::ValueEnum, Default, Debug)]
 pub enum LogType {
@@ -27,7 +54,7 @@ impl From<LogType> for LevelFilter {
     }
 }
 
-#[derive(Parser, Debug)]
+#[derive(Parser, Debug, Clone)]
 #[command(author, version, about, long_about = None)]
 pub struct Arguments {
     /// Port to run on
@@ -44,14 +71,114 @@ pub struct Arguments {
     #[arg(short, long, default_value_t = String::from("/"))]
     pub endpoint: String,
 
+    /// Response status code [default: 200]
+    #[arg(long)]
+    pub status: Option<u16>,
+
+    /// Response header as 'Name: value', can be repeated
+    #[arg(long = "header", value_parser = parse_header)]
+    pub headers: Vec<(String, String)>,
+
+    /// Serve an in-memory JSON collection as '/path' or '/path=seed.json', can be repeated
+    #[arg(long = "resource", value_parser = parse_resource)]
+    pub resources: Vec<(String, Option<PathBuf>)>,
+
+    /// Forward requests to paths no route serves to an upstream, as 'http://host:port'
+    #[arg(long)]
+    pub proxy: Option<Upstream>,
+
+    /// Folder the proxied responses are recorded to, in the --content-folder layout
+    #[arg(long, requires = "proxy")]
+    pub record: Option<PathBuf>,
+
+    /// Render {{...}} expressions in every response from the request data
+    #[arg(long, default_value_t = false)]
+    pub template: bool,
+
+    /// Write the exchanges to this HAR file on exit, and on 'e' in the TUI
+    #[arg(long)]
+    pub har_out: Option<PathBuf>,
+
+    /// Append every connection and exchange to this JSON lines file, for `view`
+    #[arg(long)]
+    pub session_log: Option<PathBuf>,
+
     #[arg(short, long, default_value_t = false)]
     pub allow_remote: bool,
+
+    /// Reload the routes when the content file, folder or config changes
+    #[arg(short, long, default_value_t = false)]
+    pub watch: bool,
+
+    /// Path prefix of the admin API
+    #[arg(long, default_value = admin::DEFAULT_PREFIX)]
+    pub admin_prefix: String,
+
+    /// Serve the admin API, which lets any client reaching the server change its routes
+    #[arg(long, default_value_t = false)]
+    pub admin: bool,
     
     #[command(flatten)]
-    pub log: Log
+    pub log: Log,
+
+    #[command(flatten)]
+    pub limits: Limits,
+
+    #[command(flatten)]
+    pub chaos: Chaos,
+
+    #[command(flatten)]
+    pub tls: Tls,
+
+    #[command(flatten)]
+    pub headless: Headless,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
 }
 
-#[derive(Args, Debug)]
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Open the TUI on a file written by --session-log, without starting a server
+    View {
+        session: PathBuf,
+    },
+}
+
+fn parse_header(header: &str) -> Result<(String, String), String> {
+    match header.split_once(':') {
+        Some((name, value)) if !name.trim().is_empty() => {
+            Ok((name.trim().to_string(), value.trim().to_string()))
+        }
+        _ => Err(format!("Header must look like 'Name: value', got: {header}")),
+    }
+}
+
+fn parse_resource(resource: &str) -> Result<(String, Option<PathBuf>), String> {
+    let (path, seed) = match resource.split_once('=') {
+        Some((path, seed)) => (path, Some(PathBuf::from(seed))),
+        None => (resource, None),
+    };
+    match path.starts_with('/') {
+        true => Ok((path.to_string(), seed)),
+        false => Err(format!("Resource path must start with '/', got: {path}")),
+    }
+}
+
+impl Arguments {
+    /// Status and headers given on the command line
+    pub fn response_meta(&self) -> EndpointMeta {
+        EndpointMeta {
+            status: self.status,
+            headers: self.headers.iter().cloned().collect(),
+            template: self.template.then_some(true),
+            // Applied by the server to every response, routes or not
+            chaos: Chaos::default(),
+        }
+    }
+}
+
+#[derive(Args, Debug, Clone)]
 #[group(multiple = false)]
 pub struct Content {
     /// Response content
@@ -64,9 +191,24 @@ pub struct Content {
 
     #[arg(long)]
     pub content_folder: Option<PathBuf>,
+
+    /// Route definition file (toml, json or yaml)
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+
+    /// Serve the responses recorded with --record
+    #[arg(long)]
+    pub replay: Option<PathBuf>,
 }
 
-#[derive(Args, Debug)]
+impl Content {
+    /// Folder to serve, either given directly or as recordings to replay
+    pub fn folder(&self) -> Option<&PathBuf> {
+        self.content_folder.as_ref().or(self.replay.as_ref())
+    }
+}
+
+#[derive(Args, Debug, Clone)]
 pub struct Log {
     /// Turn logging on
     #[arg(short, long, default_value_t=false)]
@@ -77,6 +219,42 @@ pub struct Log {
     pub log_filter: LogType
 }
 
+#[derive(Args, Debug, Clone)]
+pub struct Limits {
+    /// Maximum number of connections served at the same time
+    #[arg(long, default_value_t = 256)]
+    pub max_connections: usize,
+    /// Seconds to wait for a client to send its request
+    #[arg(long, default_value_t = 30)]
+    pub read_timeout: u64,
+    /// Seconds to wait for a client to accept the response
+    #[arg(long, default_value_t = 30)]
+    pub write_timeout: u64,
+    /// Seconds an idle keep-alive connection is held open for the next request
+    #[arg(long, default_value_t = 5)]
+    pub keep_alive_timeout: u64,
+    /// Largest request body accepted before answering 413, in bytes
+    #[arg(long, default_value_t = 16 * 1024 * 1024)]
+    pub max_body_size: usize,
+    /// Exchanges kept for the admin API and HAR export, the oldest are dropped first
+    #[arg(long, default_value_t = 1000)]
+    pub journal_limit: usize,
+}
+
+impl Limits {
+    pub fn read_timeout(&self) -> Duration {
+        Duration::from_secs(self.read_timeout)
+    }
+
+    pub fn write_timeout(&self) -> Duration {
+        Duration::from_secs(self.write_timeout)
+    }
+
+    pub fn keep_alive_timeout(&self) -> Duration {
+        Duration::from_secs(self.keep_alive_timeout)
+    }
+}
+
 #[derive(clap::ValueEnum, Clone, Default, Debug, Serialize)]
 pub enum ResponseFormat {
     #[default]
@@ -91,43 +269,82 @@ pub enum ConnectionFailedError {
     Parsing((SocketAddr, anyhow::Error)),
 }
 
+/// Identifies one accepted TCP connection for the lifetime of the server
+pub type ConnectionId = u64;
+
 #[derive(Debug)]
 pub enum Message {
     ConnectionFailed(ConnectionFailedError),
-    ConnectionReceived(Option<SocketAddr>),
-    Response(ResponseMessage),
+    ConnectionReceived(Option<SocketAddr>, ConnectionId, Option<TlsInfo>),
+    Response(Box<ResponseMessage>),
+    RoutesReloaded(reload::RoutesReloaded),
+    /// Server event for the status bar
+    Notice(String),
+    /// The request history was cleared through the admin API
+    HistoryCleared,
 }
 
 #[derive(Debug)]
 pub struct ResponseMessage {
     pub addr: SocketAddr,
+    pub connection: ConnectionId,
+    pub request: Request,
+    /// When the request was read off the connection
+    pub received: DateTime<Utc>,
     pub response: Response,
     pub method: Option<Method>,
-    pub status: Option<Status>
+    pub status: Option<Status>,
+    /// Which matcher of the route picked the response
+    pub matcher: Option<String>,
+    /// Latency and faults injected into the response
+    pub injected: Option<String>,
+    /// Whether the request came over https
+    pub secure: bool,
+    /// Time taken to answer, including injected delays
+    pub elapsed: Duration,
 }
 
 impl ResponseMessage {
-    pub fn new(addr: SocketAddr, response: &Response, method:Option<Method>, status:Option<Status>) -> Self {
+    pub fn new(addr: SocketAddr, connection: ConnectionId, request: Request, received: DateTime<Utc>, response: &Response, method:Option<Method>, status:Option<Status>) -> Self {
         ResponseMessage {
             status,
             method,
             addr,
-            response: response.clone()
+            connection,
+            request,
+            received,
+            response: response.clone(),
+            matcher: None,
+            injected: None,
+            secure: false,
+            elapsed: Duration::ZERO,
         }
     }
+
+    pub fn with_matcher(self, matcher: Option<String>) -> Self {
+        ResponseMessage { matcher, ..self }
+    }
+
+    pub fn with_injected(self, injected: Option<String>) -> Self {
+        ResponseMessage { injected, ..self }
+    }
+
+    pub fn with_timing(self, secure: bool, elapsed: Duration) -> Self {
+        ResponseMessage { secure, elapsed, ..self }
+    }
 }
 
-impl ToString for ResponseFormat {
-    fn to_string(&self) -> String {
+impl Display for ResponseFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match *self {
             ResponseFormat::Json => {
-                "application/json".to_string()
+                write!(f, "application/json")
             },
             ResponseFormat::Html => {
-                "text/html".to_string()
+                write!(f, "text/html")
             },
             ResponseFormat::None => {
-                String::new()
+                Ok(())
             }
         }
     }
@@ -155,19 +372,55 @@ impl FromStr for ResponseFormat {
 pub struct EndpointContent {
     pub content: Option<String>,
     pub format: String,
+    pub status: Status,
+    pub headers: Headers,
+    /// Body and header values are rendered with [`template::render`] for each request
+    pub template: bool,
+    /// Latency and faults applied when this response is served
+    pub chaos: Chaos,
 }
 
-impl ToString for EndpointContent {
+impl Display for EndpointContent {
     /// Turns response into http response string
-    fn to_string(&self) -> String {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match &self.content {
             Some(content) => {
-                content.to_string()
+                write!(f, "{}", content)
             },
-            None => "".to_string()
+            None => Ok(())
         }
     }
 }
+/// Status and headers laid over an endpoint, read from the cli or a `<name>.meta.json` sidecar
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EndpointMeta {
+    pub status: Option<u16>,
+    #[serde(default)]
+    pub headers: IndexMap<String, String>,
+    pub template: Option<bool>,
+    #[serde(default)]
+    pub chaos: Chaos,
+}
+
+impl EndpointMeta {
+    /// Reads the sidecar next to a content file, if there is one
+    pub fn from_sidecar(file: &Path) -> Result<Option<EndpointMeta>, FolderError> {
+        let sidecar = match file.file_stem() {
+            Some(stem) => file.with_file_name(format!("{}.meta.json", stem.to_string_lossy())),
+            None => return Ok(None),
+        };
+        if !sidecar.exists() {
+            return Ok(None);
+        }
+        let error = |e: String| FolderError {
+            error: format!("{}: {}", sidecar.display(), e),
+        };
+        let text = fs::read_to_string(&sidecar).map_err(|e| error(e.to_string()))?;
+        serde_json::from_str(&text).map(Some).map_err(|e| error(e.to_string()))
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct FolderError {
     error: String,
@@ -184,100 +437,111 @@ impl EndpointContent {
         EndpointContent {
             content: Some(String::from(content)),
             format: response_format.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Overrides the status and templating and sets the headers of `meta`
+    pub fn with_meta(mut self, meta: &EndpointMeta) -> EndpointContent {
+        if let Some(status) = meta.status {
+            self.status = Status(status);
+        }
+        if let Some(template) = meta.template {
+            self.template = template;
         }
+        self.chaos = meta.chaos.or(&self.chaos);
+        meta.headers
+            .iter()
+            .for_each(|(name, value)| self.headers.set(name, value));
+        self
     }
 
-    /// Creates a response from content file and response format
-    pub fn from_content_file(path: &PathBuf, response_format: &ResponseFormat) -> EndpointContent {
+    pub fn with_chaos(self, chaos: Chaos) -> EndpointContent {
+        EndpointContent { chaos, ..self }
+    }
+
+    /// Builds the http response served for this endpoint
+    pub fn to_response(&self) -> Response {
+        let response = match &self.content {
+            Some(content) => Response::content(content.as_str(), &self.format),
+            None => Response::empty(),
+        };
+        // Set rather than pushed so a configured Content-Type replaces the format's
+        let mut response = response.status(self.status);
+        self.headers
+            .iter()
+            .for_each(|(name, value)| response.headers.set(name, value));
+        response
+    }
+
+    /// Builds the response for `request`, rendering the content first if it is a template
+    pub fn respond_to(&self, request: &Request, params: &IndexMap<String, String>) -> Response {
+        if !self.template {
+            return self.to_response();
+        }
+        let context = TemplateContext { request, params };
+        let mut headers = Headers::new();
+        self.headers
+            .iter()
+            .for_each(|(name, value)| headers.push(name, &template::render(value, &context)));
         EndpointContent {
+            content: self.content.as_ref().map(|content| template::render(content, &context)),
+            headers,
+            template: false,
+            ..self.clone()
+        }
+        .to_response()
+    }
+
+    /// Creates a response from content file and response format, failing when the file exists
+    /// but cannot be read as UTF-8 text
+    pub fn from_content_file(path: &PathBuf, response_format: &ResponseFormat) -> Result<EndpointContent, FolderError> {
+        Ok(EndpointContent {
             content: match path.exists() {
-                true => Some(fs::read_to_string(path).expect("File is unreadable")),
+                true => Some(fs::read_to_string(path).map_err(|e| FolderError {
+                    error: format!("{}: {}", path.display(), e),
+                })?),
                 false => {
                     eprintln!("{}", format_args!("Could not find file: {:}, continuing with blank response", path.to_str().expect("Path is unparseable")));
                     None
                 }
             },
             format: response_format.to_string(),
-        }
+            ..Default::default()
+        })
     }
 
     /// Creates a response from Argument object
-    pub fn from_args(args: &Arguments) -> EndpointContent {
+    pub fn from_args(args: &Arguments) -> Result<EndpointContent, FolderError> {
         let response_format = &args.format;
         if let Some(content) = &args.content.content {
-            EndpointContent::from_content(content, response_format)
+            Ok(EndpointContent::from_content(content, response_format))
         } else if let Some(p) = &args.content.content_file {
             EndpointContent::from_content_file(p, response_format)
         } else {
-            EndpointContent {
+            Ok(EndpointContent {
                 content: None,
                 format: response_format.to_string(),
-            }
+                ..Default::default()
+            })
         }
     }
 
+    /// Creates a route per json/html file in `path` and its subdirectories, `defaults` is
+    /// applied before any sidecar.
+    ///
+    /// `api/v1/users.json` is served on `/api/v1/users` and `index.json` on its directory. A
+    /// method between name and extension (`users.delete.json`) limits the route to that method.
     pub fn from_folder(
-        path: &PathBuf,
-    ) -> Result<IndexMap<String, EndpointContent>, FolderError> {
+        path: &Path,
+        defaults: &EndpointMeta,
+    ) -> Result<RouteTable, FolderError> {
         if path.exists() {
             if path.is_dir() {
-                Ok(fs::read_dir(path).map_err(|e| 
-                        FolderError {
-                            error: format!("{}", e.to_string())
-                        })
-                    ?.filter_map(|file| match file {
-                        Ok(some) => {
-                            Some(some)
-                        },
-                        Err(e) => {
-                            warn!("{}", format!("Error: could not read directory: {:?}", e));
-                            None
-                        }
-                    }).filter_map(|file| {
-                        match file.path().is_dir() {
-                            true => None,
-                            false => {
-                                Some(file)
-                            }
-                        }
-                    })
-                    .filter_map(|file| {
-                        if let Some(ext) = file.path().extension() {
-                            match (ext.to_str(), file.path().file_stem()) {
-                                (Some("json"), Some(_)) => {
-                                   Some((ResponseFormat::Json, file.path()))
-                                },
-                                (Some("html"), Some(_)) => {
-                                    Some((ResponseFormat::Html, file.path()))
-                                },
-                                (_, Some(_)) => {
-                                    info!("{}", format!("File: {} does not have a valid extension [html, json]", file.path().to_str()?));
-                                    None
-                                },
-                                (Some(_), None) => {
-                                    info!("{}", format!("File: {} does not have a file name for use in endpoint generation", file.path().to_str()?));
-                                    None
-                                },
-                                _ => {
-                                    None
-                                }
-                            }
-                        } else {
-                            info!("{}", format!("File: {} does not have an extension, valid extensions are [html, json]", file.path().to_str()?));
-                            None
-                        }
-
-                    })
-                    .map(|(format, stem)| {
-                        let mut endpoint = String::from("/");
-                        endpoint.push_str(stem.file_stem().unwrap().to_str().unwrap());
-                        (endpoint, 
-                            EndpointContent::from_content_file(
-                                &stem,
-                                &format,
-                            ))
-                    })
-                    .collect())
+                let mut sources: IndexMap<String, PathBuf> = IndexMap::new();
+                let mut routes = RouteTable::new();
+                EndpointContent::walk_folder(path, "", defaults, &mut sources, &mut routes)?;
+                Ok(routes)
             } else {
                 Err(FolderError {error: "Path is not a directory".to_string()})
             }
@@ -285,23 +549,139 @@ impl EndpointContent {
             Err(FolderError {error: "Path does not exists".to_string()})
         }
     }
+
+    fn walk_folder(
+        dir: &Path,
+        prefix: &str,
+        defaults: &EndpointMeta,
+        sources: &mut IndexMap<String, PathBuf>,
+        routes: &mut RouteTable,
+    ) -> Result<(), FolderError> {
+        let mut entries = fs::read_dir(dir)
+            .map_err(|e| FolderError {
+                error: format!("{}: {}", dir.display(), e),
+            })?
+            .filter_map(|file| match file {
+                Ok(some) => Some(some.path()),
+                Err(e) => {
+                    warn!("{}", format!("Error: could not read directory: {:?}", e));
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+        // Sorted so conflicts are resolved the same way on every platform
+        entries.sort();
+
+        for file in entries {
+            let file_name = file.file_name().unwrap_or_default().to_string_lossy().to_string();
+            if file.is_dir() {
+                // Linked folders are skipped, a link to a parent folder would be walked forever
+                if fs::symlink_metadata(&file).is_ok_and(|meta| meta.file_type().is_symlink()) {
+                    warn!("Skipping linked folder {}", file.display());
+                    continue;
+                }
+                let prefix = format!("{}/{}", prefix, file_name);
+                EndpointContent::walk_folder(&file, &prefix, defaults, sources, routes)?;
+                continue;
+            }
+            // Sidecars describe other endpoints and are not served themselves
+            if file_name.ends_with(".meta.json") {
+                continue;
+            }
+            let format = match file.extension().and_then(|ext| ext.to_str()) {
+                Some("json") => ResponseFormat::Json,
+                Some("html") => ResponseFormat::Html,
+                Some(_) => {
+                    info!("{}", format!("File: {} does not have a valid extension [html, json]", file.display()));
+                    continue;
+                }
+                None => {
+                    info!("{}", format!("File: {} does not have an extension, valid extensions are [html, json]", file.display()));
+                    continue;
+                }
+            };
+
+            let stem = file.file_stem().unwrap_or_default().to_string_lossy().to_string();
+            let (name, method) = match stem.rsplit_once('.') {
+                Some((name, method)) => match method.to_ascii_uppercase().parse::<Method>() {
+                    Ok(method) => (name, Some(method)),
+                    Err(_) => (stem.as_str(), None),
+                },
+                None => (stem.as_str(), None),
+            };
+            let endpoint = match (name, prefix) {
+                ("index", "") => String::from("/"),
+                ("index", prefix) => prefix.to_string(),
+                (name, prefix) => format!("{}/{}", prefix, name),
+            };
+
+            let key = match method {
+                Some(method) => format!("{} {}", method, endpoint),
+                None => endpoint.clone(),
+            };
+            if let Some(existing) = sources.get(&key) {
+                let error = format!(
+                    "Endpoint {} from {} conflicts with {}, keeping the first",
+                    key,
+                    file.display(),
+                    existing.display()
+                );
+                warn!("{}", error);
+                eprintln!("{}", error);
+                continue;
+            }
+
+            let content = EndpointContent::from_content_file(&file, &format)?.with_meta(defaults);
+            let content = match EndpointMeta::from_sidecar(&file)? {
+                Some(meta) => content.with_meta(&meta),
+                None => content,
+            };
+            sources.insert(key, file.clone());
+            routes.insert(Route::new(method, &endpoint, content));
+        }
+        Ok(())
+    }
+}
+
+/// Builds the route table for the content options, printing the endpoints it found
+pub fn populate_routes(args: &Arguments) -> RouteTable {
+    match load_routes(args) {
+        Ok(routes) => {
+            if args.content.folder().is_some() || args.content.config.is_some() || !args.resources.is_empty() {
+                eprintln!(
+                    "Valid endpoints: {:?}",
+                    routes.iter().map(Route::to_string).collect::<Vec<_>>()
+                );
+            }
+            routes
+        }
+        Err(e) => {
+            warn!("{}", e);
+            eprintln!("{}", e);
+            RouteTable::new()
+        }
+    }
 }
 
-pub fn populate_map(args: &Arguments) -> IndexMap<String, EndpointContent> {
-    let mut map: IndexMap<String, EndpointContent> = IndexMap::new();
-    let (content, content_file, content_folder) = (
+/// Builds the route table for the content options
+pub fn load_routes(args: &Arguments) -> Result<RouteTable> {
+    let mut routes = RouteTable::new();
+    let (content, content_file, content_folder, config) = (
         &args.content.content,
         &args.content.content_file,
-        &args.content.content_folder,
+        args.content.folder(),
+        &args.content.config,
     );
-    match (content, content_file, content_folder) {
-        (Some(content), None, None) => {
-            map.insert(
-                args.endpoint.clone(),
-                EndpointContent::from_content(content, &args.format),
-            );
+    let meta = args.response_meta();
+    match (content, content_file, content_folder, config) {
+        (Some(content), None, None, None) => {
+            routes.insert(Route::new(
+                None,
+                &args.endpoint,
+                EndpointContent::from_content(content, &args.format).with_meta(&meta),
+            ));
         }
-        (None, Some(content_file), None) => {
+        (None, Some(content_file), None, None) => {
             let endpoint = match content_file.file_stem() {
                 Some(path) => {
                     let mut endpoint: String = String::from("/");
@@ -310,22 +690,35 @@ pub fn populate_map(args: &Arguments) -> IndexMap<String, EndpointContent> {
                 }
                 None => String::from("/"),
             };
-            map.insert(endpoint, EndpointContent::from_content_file(content_file, &args.format));
+            let content = EndpointContent::from_content_file(content_file, &args.format)
+                .map_err(|e| anyhow!("Error while reading content file: {}", e))?;
+            routes.insert(Route::new(None, &endpoint, content.with_meta(&meta)));
         }
-        (None, None, Some(content_folder)) => {
-            match EndpointContent::from_folder(content_folder) {
-                Ok(map_b) => {
-                    eprintln!("Valid endpoints: {:?}", map_b.keys().collect::<Vec<_>>());
-                    map.extend(map_b)
-                }
-                Err(e) => {
-                    warn!("Error while parsing content folder: {:?}", e);
-                }
-            }
+        (None, None, Some(content_folder), None) => {
+            routes = EndpointContent::from_folder(content_folder, &meta)
+                .map_err(|e| anyhow!("Error while parsing content folder: {}", e))?;
+        }
+        (None, None, None, Some(config)) => {
+            let base = config.parent().map(PathBuf::from).unwrap_or_default();
+            routes = RouteConfig::from_file(config)
+                .and_then(|config| config.into_routes(&base, &meta))
+                .map_err(|e| anyhow!("Error while parsing config file: {}", e))?;
         }
         _ => {
-            map.insert(String::from("/"), EndpointContent::default());
+            routes.insert(Route::new(
+                None,
+                "/",
+                EndpointContent::default().with_meta(&meta),
+            ));
+        }
+    }
+    for (path, seed) in &args.resources {
+        let resource = match seed {
+            Some(seed) => Resource::from_seed_file(path, seed),
+            None => Resource::new(path, None),
         }
+        .map_err(|e| anyhow!("Error while loading resource: {}", e))?;
+        routes.extend(resource.into_routes());
     }
-    map
+    Ok(routes)
 }
@@ -1,4 +1,5 @@
 use anyhow::Result;
+use chrono::Local;
 use crossterm::{event::EventStream, execute, QueueableCommand};
 use futures::FutureExt;
 use std::{net::IpAddr, sync::Arc};
@@ -9,12 +10,25 @@ use futures::{lock::Mutex, StreamExt};
 use indexmap::IndexMap;
 use simplelog::*;
 use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::Duration;
-use tokio::sync::mpsc::channel;
+use tokio::net::TcpListener;
+use tokio::sync::{mpsc::channel, mpsc::Receiver, mpsc::Sender, watch, Semaphore};
+use tokio::time::sleep;
+use tokio_rustls::TlsAcceptor;
 pub mod tui;
 use clap::Parser;
 use std::io::stdout;
-use testsuite::{populate_map, Arguments, ConnectionFailedError, EndpointContent, Message};
+use testsuite::admin::Admin;
+use testsuite::har::{write_har, DEFAULT_HAR};
+use testsuite::headless::{self, Headless};
+use testsuite::journal::Journal;
+use testsuite::proxy::Proxy;
+use testsuite::reload::watch_routes;
+use testsuite::routes::RouteTable;
+use testsuite::session;
+use testsuite::{populate_routes, Arguments, Command, ConnectionFailedError, Message};
 use tui::{TuiResponse, *};
 
 use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
@@ -28,8 +42,6 @@ pub type Connections = IndexMap<IpAddr, Vec<TuiResponse>>;
 
 #[tokio::main]
 async fn main() -> Result<(), anyhow::Error> {
-    let connections_ref: Arc<Mutex<Connections>> = Arc::new(Mutex::new(Connections::new())); //connections_mutex
-
     let args = Arguments::parse();
 
     CombinedLogger::init(vec![WriteLogger::new(
@@ -39,10 +51,16 @@ async fn main() -> Result<(), anyhow::Error> {
     )])
     .unwrap();
 
+    if let Some(Command::View { session }) = &args.command {
+        return view_session(session, &args).await;
+    }
+
     let port = args.port;
 
-    let map = populate_map(&args);
-    let map_ref = Arc::from(map.clone());
+    let routes = populate_routes(&args);
+    let (routes_sender, routes_ref) = watch::channel(Arc::new(routes));
+    let routes_sender = Arc::new(routes_sender);
+    let routes_receiver = routes_ref.clone();
 
     let host = match &args.allow_remote {
         true => "0.0.0.0",
@@ -50,35 +68,138 @@ async fn main() -> Result<(), anyhow::Error> {
     };
     let end_point: String = host.to_owned() + ":" + &port.to_string();
 
-    let listener = tokio::net::TcpListener::bind(&end_point).await?;
-    let (request_sender, mut request_receiver) = channel::<Message>(100);
-
-    let server = tokio::spawn(async move {
-        let request_sender = request_sender.clone();
-        let reference = Arc::clone(&map_ref);
-        loop {
-            match listener.accept().await {
-                Ok((socket, addr)) => {
-                    if let Err(err) =
-                        handle_connection(addr, socket, &reference, request_sender.clone()).await
-                    {
-                        warn!(
-                            "Could not parse request from address: {:}, error:{:}",
-                            addr, err
-                        );
-                        let _ = request_sender
-                            .send(Message::ConnectionFailed(ConnectionFailedError::Parsing((
-                                addr, err,
-                            ))))
-                            .await;
-                    }
-                }
-                Err(err) => {
-                    warn!("Could not receive connection:{:}", err);
-                }
-            }
+    let tls = args.tls.acceptor()?;
+    let listener = TcpListener::bind(&end_point).await?;
+    let (request_sender, request_receiver) = channel::<Message>(100);
+    let request_receiver = match &args.session_log {
+        Some(path) => session::record(request_receiver, path).await?,
+        None => request_receiver,
+    };
+
+    let watcher = args.watch.then(|| {
+        tokio::spawn(watch_routes(
+            args.clone(),
+            Arc::clone(&routes_sender),
+            request_sender.clone(),
+        ))
+    });
+
+    let journal = Arc::new(Journal::new(args.limits.journal_limit));
+    let settings = Arc::new(Settings {
+        limits: args.limits.clone(),
+        proxy: args
+            .proxy
+            .clone()
+            .map(|upstream| Proxy::new(upstream, args.record.clone(), &args.limits)),
+        chaos: args.chaos.clone(),
+        admin: args.admin.then(|| {
+            Admin::new(
+                &args.admin_prefix,
+                Arc::clone(&routes_sender),
+                Arc::clone(&journal),
+                request_sender.clone(),
+                args.response_meta(),
+            )
+        }),
+        journal,
+    });
+    let connection_slots = Arc::new(Semaphore::new(settings.limits.max_connections));
+
+    let next_connection = Arc::new(AtomicU64::new(0));
+    let accept = |listener: TcpListener, tls: Option<TlsAcceptor>| {
+        tokio::spawn(accept_connections(
+            listener,
+            tls,
+            routes_ref.clone(),
+            request_sender.clone(),
+            Arc::clone(&settings),
+            Arc::clone(&connection_slots),
+            Arc::clone(&next_connection),
+        ))
+    };
+    let servers = match (tls, args.tls.tls_port) {
+        (Some(tls), Some(tls_port)) => {
+            let tls_listener = TcpListener::bind((host, tls_port)).await?;
+            vec![accept(listener, None), accept(tls_listener, Some(tls))]
+        }
+        (tls, _) => vec![accept(listener, tls)],
+    };
+
+    let shutdown_reason = match args.headless.headless {
+        true => run_headless(request_receiver, &args.headless).await,
+        false => {
+            let har_out = args
+                .har_out
+                .clone()
+                .unwrap_or_else(|| PathBuf::from(DEFAULT_HAR));
+            run_tui(
+                routes_receiver,
+                request_receiver,
+                Arc::clone(&settings.journal),
+                har_out,
+            )
+            .await?
+        }
+    };
+    if let Some(har_out) = &args.har_out {
+        match write_har(har_out, &settings.journal.exchanges()) {
+            Ok(()) => eprintln!("Wrote the exchanges to {}", har_out.display()),
+            Err(err) => eprintln!("{err:#}"),
+        }
+    }
+    info!("Shutting down server due to: {shutdown_reason}");
+    if args.headless.headless {
+        eprintln!("Shutting down server due to: {shutdown_reason}");
+    }
+
+    servers.iter().for_each(|server| server.abort());
+    if let Some(watcher) = watcher {
+        watcher.abort();
+    }
+    Ok(())
+}
+
+/// Shows the exchanges of a session log in the TUI
+async fn view_session(session: &Path, args: &Arguments) -> Result<()> {
+    let (messages, skipped) = session::load(session)?;
+    let journal = Arc::new(Journal::new(args.limits.journal_limit));
+    let mut exchanges = 0;
+    messages.iter().for_each(|message| {
+        if let Message::Response(message) = message {
+            journal.record(message);
+            exchanges += 1;
         }
     });
+    let mut notice = format!("Viewing {} ({exchanges} exchanges)", session.display());
+    if skipped > 0 {
+        notice.push_str(&format!(", skipped {skipped} unreadable lines"));
+    }
+
+    let (sender, receiver) = channel::<Message>(100);
+    tokio::spawn(async move {
+        for message in messages {
+            let _ = sender.send(message).await;
+        }
+        let _ = sender.send(Message::Notice(notice)).await;
+    });
+    // Nothing is served, the empty table only gives 'r' something to reset
+    let (_routes, routes_receiver) = watch::channel(Arc::new(RouteTable::new()));
+    let har_out = args
+        .har_out
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_HAR));
+    run_tui(routes_receiver, receiver, journal, har_out).await?;
+    Ok(())
+}
+
+/// Shows the TUI until the user quits, returning why
+async fn run_tui(
+    routes_receiver: watch::Receiver<Arc<RouteTable>>,
+    mut request_receiver: Receiver<Message>,
+    journal: Arc<Journal>,
+    har_out: PathBuf,
+) -> Result<String> {
+    let connections_ref: Arc<Mutex<Connections>> = Arc::new(Mutex::new(Connections::new())); //connections_mutex
 
     execute!(stdout(), crossterm::cursor::Hide)?;
     let stdout = stdout();
@@ -87,23 +208,46 @@ async fn main() -> Result<(), anyhow::Error> {
     let mut reader = EventStream::new();
     let mut exit_reason = None::<String>;
     let tuistate = Arc::new(Mutex::new(
-        TuiState::new(Arc::clone(&connections_ref)).await,
+        TuiState::new(
+            Arc::clone(&connections_ref),
+            routes_receiver,
+            journal,
+            har_out,
+        )
+        .await,
     ));
     let tui_ref = Arc::clone(&tuistate);
 
     let message_client = tokio::spawn(async move {
-        loop {
-            if let Some(message) = request_receiver.recv().await {
-                handle_message(message, Arc::clone(&connections_ref)).await;
-                Arc::clone(&tui_ref).lock().await.needs_update = true;
+        while let Some(message) = request_receiver.recv().await {
+            match message {
+                Message::RoutesReloaded(reloaded) => {
+                    Arc::clone(&tui_ref).lock().await.notice =
+                        Some(format!("{} {}", Local::now().format("%H:%M:%S"), reloaded));
+                }
+                Message::Notice(notice) => {
+                    Arc::clone(&tui_ref).lock().await.notice =
+                        Some(format!("{} {}", Local::now().format("%H:%M:%S"), notice));
+                }
+                Message::HistoryCleared => {
+                    connections_ref.lock().await.clear();
+                    let mut tui = tui_ref.lock().await;
+                    tui.clear_history();
+                    tui.notice = Some(format!(
+                        "{} Admin cleared the request history",
+                        Local::now().format("%H:%M:%S")
+                    ));
+                }
+                message => handle_message(message, Arc::clone(&connections_ref)).await,
             }
+            Arc::clone(&tui_ref).lock().await.needs_update = true;
         }
     });
 
     let shutdown_reason = loop {
         let out = Arc::clone(&out);
-        if exit_reason.is_some() {
-            break exit_reason.unwrap();
+        if let Some(exit_reason) = exit_reason.take() {
+            break exit_reason;
         }
         let delay = futures_timer::Delay::new(Duration::from_millis(REFRESH_RATE)).fuse();
         tokio::select! {
@@ -134,7 +278,122 @@ async fn main() -> Result<(), anyhow::Error> {
     out.lock().await.queue(crossterm::cursor::MoveTo(0, 1))?;
 
     message_client.abort();
-    server.abort();
     disable_raw_mode()?;
-    Ok(())
+    Ok(shutdown_reason)
+}
+
+/// Prints the exchanges until a signal or one of the exit conditions stops the server,
+/// returning why
+async fn run_headless(mut request_receiver: Receiver<Message>, headless: &Headless) -> String {
+    let deadline = sleep(
+        headless
+            .exit_timeout
+            .map(Duration::from_secs)
+            .unwrap_or(Duration::MAX),
+    );
+    tokio::pin!(deadline);
+    let mut served = 0;
+    loop {
+        tokio::select! {
+            message = request_receiver.recv() => match message {
+                Some(Message::Response(message)) => {
+                    served += 1;
+                    println!("{}", headless.output.exchange(&message, served));
+                    if headless.exit_after.is_some_and(|limit| served >= limit as u64) {
+                        return format!("served {served} requests");
+                    }
+                }
+                Some(message) => {
+                    if let Some(event) = headless::event(&message) {
+                        eprintln!("{event}");
+                    }
+                }
+                None => return "server stopped".to_string(),
+            },
+            _ = &mut deadline, if headless.exit_timeout.is_some() => {
+                return format!("ran for {} seconds", headless.exit_timeout.unwrap_or_default());
+            }
+            signal = shutdown_signal() => return signal.to_string(),
+        }
+    }
+}
+
+/// Waits for Ctrl-C, or SIGTERM on unix
+async fn shutdown_signal() -> &'static str {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let mut terminate = match signal(SignalKind::terminate()) {
+            Ok(terminate) => terminate,
+            Err(err) => {
+                warn!("Could not listen for SIGTERM: {err}");
+                let _ = tokio::signal::ctrl_c().await;
+                return "SIGINT";
+            }
+        };
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => "SIGINT",
+            _ = terminate.recv() => "SIGTERM",
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+        "Ctrl-C"
+    }
+}
+
+/// Serves the connections of one listener, `tls` makes it an https listener
+async fn accept_connections(
+    listener: TcpListener,
+    tls: Option<TlsAcceptor>,
+    routes_ref: watch::Receiver<Arc<RouteTable>>,
+    request_sender: Sender<Message>,
+    settings: Arc<Settings>,
+    connection_slots: Arc<Semaphore>,
+    next_connection: Arc<AtomicU64>,
+) {
+    loop {
+        // Wait for a free slot before accepting so clients queue in the backlog
+        let permit = Arc::clone(&connection_slots)
+            .acquire_owned()
+            .await
+            .expect("connection semaphore is never closed");
+        match listener.accept().await {
+            Ok((socket, addr)) => {
+                let connection = next_connection.fetch_add(1, Ordering::SeqCst) + 1;
+                let reference = routes_ref.clone();
+                let request_sender = request_sender.clone();
+                let settings = Arc::clone(&settings);
+                let tls = tls.clone();
+                tokio::spawn(async move {
+                    if let Err(err) = handle_connection(
+                        addr,
+                        connection,
+                        socket,
+                        tls.as_ref(),
+                        &reference,
+                        request_sender.clone(),
+                        &settings,
+                    )
+                    .await
+                    {
+                        warn!(
+                            "Could not parse request from address: {:}, error:{:}",
+                            addr, err
+                        );
+                        let _ = request_sender
+                            .send(Message::ConnectionFailed(ConnectionFailedError::Parsing((
+                                addr, err,
+                            ))))
+                            .await;
+                    }
+                    drop(permit);
+                });
+            }
+            Err(err) => {
+                warn!("Could not receive connection:{:}", err);
+            }
+        }
+    }
 }
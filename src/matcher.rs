@@ -0,0 +1,154 @@
+use crate::http::Request;
+use crate::EndpointContent;
+use regex::Regex;
+use serde_json::Value;
+use std::fmt::Display;
+
+/// Part of the request a condition looks at
+#[derive(Debug, Clone)]
+pub enum Subject {
+    Header(String),
+    Query(String),
+    /// Field of a JSON body, given as a JSON path like `$.items[0].type`
+    Json(String),
+    /// The whole body as text
+    Body,
+}
+
+#[derive(Debug, Clone)]
+pub enum Predicate {
+    Equals(String),
+    Regex(Regex),
+    /// `true` requires the subject to be there, `false` requires it to be missing
+    Present(bool),
+}
+
+#[derive(Debug, Clone)]
+pub struct Condition {
+    pub subject: Subject,
+    pub predicate: Predicate,
+}
+
+/// Alternative response for a route, picked when all of its conditions hold
+#[derive(Debug, Clone)]
+pub struct Matcher {
+    pub conditions: Vec<Condition>,
+    pub response: EndpointContent,
+}
+
+/// Converts `$.items[0].type` into the JSON pointer `/items/0/type`
+pub fn json_pointer(path: &str) -> Result<String, String> {
+    let rest = path
+        .strip_prefix('$')
+        .ok_or_else(|| format!("JSON path must start with '$', got: {path}"))?;
+    let mut pointer = String::new();
+    let mut chars = rest.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '.' => pointer.push('/'),
+            '[' => {
+                pointer.push('/');
+                for c in chars.by_ref() {
+                    match c {
+                        ']' => break,
+                        '\'' | '"' => {}
+                        '~' => pointer.push_str("~0"),
+                        '/' => pointer.push_str("~1"),
+                        c => pointer.push(c),
+                    }
+                }
+            }
+            '~' => pointer.push_str("~0"),
+            '/' => pointer.push_str("~1"),
+            c => pointer.push(c),
+        }
+    }
+    Ok(pointer)
+}
+
+impl Condition {
+    pub fn holds(&self, request: &Request) -> bool {
+        let value = match &self.subject {
+            Subject::Header(name) => request.headers.get(name).map(str::to_string),
+            Subject::Query(name) => request
+                .query_params()
+                .into_iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value),
+            Subject::Json(path) => {
+                let body = serde_json::from_slice::<Value>(&request.body).unwrap_or_default();
+                match json_pointer(path)
+                    .ok()
+                    .and_then(|pointer| body.pointer(&pointer).cloned())
+                {
+                    Some(Value::String(text)) => Some(text),
+                    Some(Value::Null) | None => None,
+                    Some(value) => Some(value.to_string()),
+                }
+            }
+            Subject::Body => Some(request.body_text().to_string()).filter(|body| !body.is_empty()),
+        };
+        match (&self.predicate, value) {
+            (Predicate::Present(present), value) => *present == value.is_some(),
+            (Predicate::Equals(expected), Some(value)) => *expected == value,
+            (Predicate::Regex(regex), Some(value)) => regex.is_match(&value),
+            (_, None) => false,
+        }
+    }
+}
+
+impl Display for Condition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.subject {
+            Subject::Header(name) => write!(f, "header {name}")?,
+            Subject::Query(name) => write!(f, "query {name}")?,
+            Subject::Json(path) => write!(f, "json {path}")?,
+            Subject::Body => write!(f, "body")?,
+        }
+        match &self.predicate {
+            Predicate::Equals(expected) => write!(f, " == {expected}"),
+            Predicate::Regex(regex) => write!(f, " ~ {regex}"),
+            Predicate::Present(true) => write!(f, " present"),
+            Predicate::Present(false) => write!(f, " absent"),
+        }
+    }
+}
+
+impl Matcher {
+    pub fn matches(&self, request: &Request) -> bool {
+        self.conditions
+            .iter()
+            .all(|condition| condition.holds(request))
+    }
+}
+
+impl Display for Matcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.conditions.is_empty() {
+            true => write!(f, "always"),
+            false => write!(
+                f,
+                "{}",
+                self.conditions
+                    .iter()
+                    .map(Condition::to_string)
+                    .collect::<Vec<_>>()
+                    .join(" and ")
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_json_paths_to_pointers() {
+        assert_eq!(json_pointer("$").unwrap(), "");
+        assert_eq!(json_pointer("$.user.name").unwrap(), "/user/name");
+        assert_eq!(json_pointer("$.items[0].type").unwrap(), "/items/0/type");
+        assert_eq!(json_pointer("$['a/b'].c~d").unwrap(), "/a~1b/c~0d");
+        assert!(json_pointer("user.name").is_err());
+    }
+}
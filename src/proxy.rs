@@ -0,0 +1,252 @@
+use crate::http::{read_response, Headers, Request, Response, Status};
+use crate::{Limits, ResponseFormat};
+use log::{info, warn};
+use std::{
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncWriteExt, BufReader},
+    net::TcpStream,
+    time::timeout,
+};
+
+/// Headers that describe a single connection and are not passed through
+const HOP_BY_HOP: [&str; 8] = [
+    "Connection",
+    "Keep-Alive",
+    "Proxy-Authenticate",
+    "Proxy-Authorization",
+    "TE",
+    "Trailer",
+    "Transfer-Encoding",
+    "Upgrade",
+];
+
+/// Plain http server requests are forwarded to, as `http://host[:port][/base]`
+#[derive(Debug, Clone)]
+pub struct Upstream {
+    pub host: String,
+    pub port: u16,
+    /// Path prefixed to every forwarded request
+    pub base: String,
+}
+
+impl FromStr for Upstream {
+    type Err = String;
+    fn from_str(url: &str) -> Result<Self, Self::Err> {
+        let rest = url
+            .strip_prefix("http://")
+            .ok_or_else(|| format!("Only http:// upstreams are supported, got: {url}"))?;
+        let (authority, base) = match rest.find('/') {
+            Some(index) => (&rest[..index], rest[index..].trim_end_matches('/')),
+            None => (rest, ""),
+        };
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => (
+                host,
+                port.parse::<u16>()
+                    .map_err(|_| format!("Invalid upstream port: {port}"))?,
+            ),
+            None => (authority, 80),
+        };
+        if host.is_empty() {
+            return Err(format!("Upstream has no host: {url}"));
+        }
+        Ok(Upstream {
+            host: host.to_string(),
+            port,
+            base: base.to_string(),
+        })
+    }
+}
+
+impl Display for Upstream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "http://{}:{}{}", self.host, self.port, self.base)
+    }
+}
+
+/// Forwards requests no route answers to an upstream, optionally recording the responses
+#[derive(Debug)]
+pub struct Proxy {
+    pub upstream: Upstream,
+    /// Folder the responses are written to, laid out for `--content-folder`
+    pub record: Option<PathBuf>,
+    timeout: Duration,
+    max_body_size: usize,
+}
+
+impl Proxy {
+    pub fn new(upstream: Upstream, record: Option<PathBuf>, limits: &Limits) -> Self {
+        Proxy {
+            upstream,
+            record,
+            timeout: limits.read_timeout(),
+            max_body_size: limits.max_body_size,
+        }
+    }
+
+    /// Sends `request` upstream and returns its response, a 502 if it could not be reached
+    pub async fn forward(&self, request: &Request) -> Response {
+        let response = match timeout(self.timeout, self.exchange(request)).await {
+            Ok(Ok(response)) => response,
+            Ok(Err(err)) => return self.bad_gateway(err),
+            Err(_) => return self.bad_gateway("timed out".to_string()),
+        };
+        info!(
+            "Proxied {} {} to {}: {}",
+            request.method,
+            request.target(),
+            self.upstream,
+            response.status
+        );
+        if let Some(folder) = &self.record {
+            match record(folder, request, &response) {
+                Ok(file) => info!("Recorded {}", file.display()),
+                Err(err) => warn!("Could not record {}: {}", request.target(), err),
+            }
+        }
+        response
+    }
+
+    async fn exchange(&self, request: &Request) -> Result<Response, String> {
+        let stream = TcpStream::connect((self.upstream.host.as_str(), self.upstream.port))
+            .await
+            .map_err(|e| e.to_string())?;
+        let (read_half, mut write_half) = stream.into_split();
+
+        let mut head = format!(
+            "{} {}{} HTTP/1.1\r\n",
+            request.method,
+            self.upstream.base,
+            request.target()
+        );
+        head.push_str(&format!(
+            "Host: {}:{}\r\n",
+            self.upstream.host, self.upstream.port
+        ));
+        request
+            .headers
+            .iter()
+            .filter(|(name, _)| !is_hop_by_hop(name))
+            // The body is sent right away, so there is no 100 Continue to wait for
+            .filter(|(name, _)| {
+                !["Host", "Content-Length", "Expect"]
+                    .iter()
+                    .any(|skip| skip.eq_ignore_ascii_case(name))
+            })
+            .for_each(|(name, value)| head.push_str(&format!("{name}: {value}\r\n")));
+        if !request.body.is_empty() {
+            head.push_str(&format!("Content-Length: {}\r\n", request.body.len()));
+        }
+        head.push_str("Connection: close\r\n\r\n");
+
+        write_half
+            .write_all(head.as_bytes())
+            .await
+            .map_err(|e| e.to_string())?;
+        write_half
+            .write_all(&request.body)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let mut reader = BufReader::new(read_half);
+        let mut response = read_response(&mut reader, request.method, self.max_body_size)
+            .await
+            .map_err(|e| e.to_string())?;
+        // The body is passed on decoded and gets a fresh length
+        let mut headers = Headers::new();
+        response
+            .headers
+            .iter()
+            .filter(|(name, _)| {
+                !is_hop_by_hop(name) && !name.eq_ignore_ascii_case("Content-Length")
+            })
+            .for_each(|(name, value)| headers.push(name, value));
+        response.headers = headers;
+        response.version = "HTTP/1.1".to_string();
+        Ok(response)
+    }
+
+    fn bad_gateway(&self, error: String) -> Response {
+        warn!("Upstream {} failed: {}", self.upstream, error);
+        Response::content(
+            format!("Upstream {} failed: {}", self.upstream, error),
+            "text/plain",
+        )
+        .status(Status::BAD_GATEWAY)
+    }
+}
+
+fn is_hop_by_hop(name: &str) -> bool {
+    HOP_BY_HOP
+        .iter()
+        .any(|header| header.eq_ignore_ascii_case(name))
+}
+
+/// Writes the response as `<path>.<method>.<json|html>` with a `.meta.json` sidecar holding the
+/// status and headers, so the folder can be served again with `--replay`.
+///
+/// Requests differing only in their query string share a file, the last one is kept.
+fn record(folder: &Path, request: &Request, response: &Response) -> Result<PathBuf, String> {
+    let body = std::str::from_utf8(&response.body)
+        .map_err(|_| "binary bodies are not recorded".to_string())?;
+    let mut segments = request
+        .path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>();
+    // `\` and `:` would make a segment a path of its own on Windows
+    if segments
+        .iter()
+        .any(|segment| *segment == ".." || segment.contains(['\\', ':']))
+    {
+        return Err("path leaves the record folder".to_string());
+    }
+    let name = segments.pop().unwrap_or("index");
+
+    let content_type = response.headers.get("Content-Type").unwrap_or_default();
+    let format = match content_type.contains("html") {
+        true => ResponseFormat::Html,
+        false => ResponseFormat::Json,
+    };
+    let extension = match format {
+        ResponseFormat::Html => "html",
+        _ => "json",
+    };
+    let method = request.method.to_string().to_ascii_lowercase();
+
+    let dir = segments
+        .iter()
+        .fold(folder.to_path_buf(), |dir, segment| dir.join(segment));
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let file = dir.join(format!("{name}.{method}.{extension}"));
+    let sidecar = dir.join(format!("{name}.{method}.meta.json"));
+
+    // Content-Type follows from the extension unless the upstream sent something else
+    let headers = response
+        .headers
+        .iter()
+        .filter(|(name, _)| !name.eq_ignore_ascii_case("Date"))
+        .filter(|(name, value)| {
+            !(name.eq_ignore_ascii_case("Content-Type") && *value == format.to_string())
+        })
+        .map(|(name, value)| (name.to_string(), serde_json::Value::from(value)))
+        .collect::<serde_json::Map<_, _>>();
+    let meta = serde_json::json!({
+        "status": response.status.code(),
+        "headers": headers,
+    });
+
+    fs::write(&file, body).map_err(|e| e.to_string())?;
+    fs::write(
+        &sidecar,
+        serde_json::to_string_pretty(&meta).unwrap_or_default(),
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(file)
+}
@@ -0,0 +1,200 @@
+use crate::config::RouteConfig;
+use crate::har::DEFAULT_HAR;
+use crate::routes::RouteTable;
+use crate::{load_routes, Arguments, Message};
+use log::{info, warn};
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+use tokio::sync::{mpsc, watch};
+
+/// How often watched files are checked for changes
+pub const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Endpoints that appeared or disappeared when the routes were rebuilt
+#[derive(Debug, Clone)]
+pub struct RoutesReloaded {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub total: usize,
+}
+
+impl RoutesReloaded {
+    pub fn between(old: &RouteTable, new: &RouteTable) -> Self {
+        let old_routes = old
+            .iter()
+            .map(|route| route.to_string())
+            .collect::<Vec<_>>();
+        let new_routes = new
+            .iter()
+            .map(|route| route.to_string())
+            .collect::<Vec<_>>();
+        RoutesReloaded {
+            added: new_routes
+                .iter()
+                .filter(|route| !old_routes.contains(route))
+                .cloned()
+                .collect(),
+            removed: old_routes
+                .iter()
+                .filter(|route| !new_routes.contains(route))
+                .cloned()
+                .collect(),
+            total: new.len(),
+        }
+    }
+}
+
+impl Display for RoutesReloaded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Routes reloaded ({} endpoints)", self.total)?;
+        if !self.added.is_empty() {
+            write!(f, ", added: {}", self.added.join(" "))?;
+        }
+        if !self.removed.is_empty() {
+            write!(f, ", removed: {}", self.removed.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Files whose changes trigger a reload: the content file or folder, the config with the body
+/// files it references, and the resource seeds
+fn watched_paths(args: &Arguments) -> Vec<PathBuf> {
+    let content = &args.content;
+    let body_files = content
+        .config
+        .as_ref()
+        .and_then(|config| {
+            let base = config.parent().map(PathBuf::from).unwrap_or_default();
+            let files = RouteConfig::from_file(config).ok()?;
+            Some(
+                files
+                    .body_files()
+                    .into_iter()
+                    .map(|file| base.join(file))
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .unwrap_or_default();
+    [
+        content.content_file.clone(),
+        content.folder().cloned(),
+        content.config.clone(),
+    ]
+    .into_iter()
+    .flatten()
+    .chain(body_files)
+    .chain(args.resources.iter().filter_map(|(_, seed)| seed.clone()))
+    .map(|path| absolute(&path))
+    .collect()
+}
+
+/// Files the server writes itself, left out of watched folders so writing them does not
+/// trigger a reload
+fn output_paths(args: &Arguments) -> Vec<PathBuf> {
+    [
+        Some(args.log.log_file.clone()),
+        args.session_log.clone(),
+        Some(
+            args.har_out
+                .clone()
+                .unwrap_or_else(|| PathBuf::from(DEFAULT_HAR)),
+        ),
+        args.record.clone(),
+    ]
+    .into_iter()
+    .flatten()
+    .map(|path| absolute(&path))
+    .collect()
+}
+
+/// `path` with symlinks and `.` resolved, so it compares equal however it was given
+fn absolute(path: &Path) -> PathBuf {
+    fs::canonicalize(path)
+        .ok()
+        .or_else(|| {
+            let parent = match path.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent,
+                _ => Path::new("."),
+            };
+            Some(fs::canonicalize(parent).ok()?.join(path.file_name()?))
+        })
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+type Snapshot = BTreeMap<PathBuf, Option<SystemTime>>;
+
+fn snapshot(paths: &[PathBuf], outputs: &[PathBuf]) -> Snapshot {
+    let mut snapshot = Snapshot::new();
+    paths.iter().for_each(|path| {
+        // A folder that is itself an output, like recordings being replayed, is still watched
+        let outputs = outputs
+            .iter()
+            .filter(|output| !path.starts_with(output))
+            .cloned()
+            .collect::<Vec<_>>();
+        collect_modified(path, &outputs, &mut snapshot)
+    });
+    snapshot
+}
+
+fn collect_modified(path: &Path, outputs: &[PathBuf], snapshot: &mut Snapshot) {
+    if outputs.iter().any(|output| path.starts_with(output)) {
+        return;
+    }
+    let modified = fs::metadata(path).and_then(|meta| meta.modified()).ok();
+    snapshot.insert(path.to_path_buf(), modified);
+    if path.is_dir() {
+        if let Ok(entries) = fs::read_dir(path) {
+            entries
+                .flatten()
+                .for_each(|entry| collect_modified(&entry.path(), outputs, snapshot));
+        }
+    }
+}
+
+/// Polls the content options for changes and publishes a rebuilt route table.
+///
+/// A table that fails to load is reported and the previous one stays in place.
+pub async fn watch_routes(
+    args: Arguments,
+    routes: Arc<watch::Sender<Arc<RouteTable>>>,
+    messages: mpsc::Sender<Message>,
+) {
+    let outputs = output_paths(&args);
+    let mut paths = watched_paths(&args);
+    let mut last = snapshot(&paths, &outputs);
+    let mut interval = tokio::time::interval(WATCH_INTERVAL);
+    loop {
+        interval.tick().await;
+        let current = snapshot(&paths, &outputs);
+        if current == last {
+            continue;
+        }
+        // The config may now reference other body files
+        paths = watched_paths(&args);
+        last = snapshot(&paths, &outputs);
+
+        match load_routes(&args) {
+            Ok(table) => {
+                let mut reloaded = None;
+                routes.send_modify(|current| {
+                    reloaded = Some(RoutesReloaded::between(current, &table));
+                    *current = Arc::new(table);
+                });
+                let reloaded = reloaded.expect("send_modify runs the closure");
+                info!("{}", reloaded);
+                let _ = messages.send(Message::RoutesReloaded(reloaded)).await;
+            }
+            Err(err) => {
+                warn!("Keeping previous routes, reload failed: {}", err);
+            }
+        }
+    }
+}
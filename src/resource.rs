@@ -0,0 +1,280 @@
+use crate::http::{Method, Request, Response, Status};
+use crate::routes::Route;
+use crate::{EndpointContent, ResponseFormat};
+use indexmap::IndexMap;
+use serde_json::{Map, Value};
+use std::{
+    error::Error,
+    fmt::Display,
+    fs,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+/// Path parameter the routes of a resource capture the item id in
+const ID_PARAM: &str = "id";
+
+type Item = Map<String, Value>;
+
+#[derive(Debug)]
+pub struct ResourceError {
+    error: String,
+}
+
+impl Display for ResourceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.error)
+    }
+}
+
+impl Error for ResourceError {}
+
+#[derive(Debug, Default)]
+struct Collection {
+    items: Vec<Item>,
+    next_id: u64,
+}
+
+impl Collection {
+    fn position(&self, id: &str) -> Option<usize> {
+        self.items
+            .iter()
+            .position(|item| item.get("id").is_some_and(|value| id_text(value) == id))
+    }
+}
+
+/// Ids are compared as text so `/users/1` finds both `"id": 1` and `"id": "1"`
+fn id_text(id: &Value) -> String {
+    match id {
+        Value::String(text) => text.clone(),
+        id => id.to_string(),
+    }
+}
+
+/// JSON collection served with create, read, update and delete routes under one path.
+///
+/// The items live in memory only, they start from the seed every time the routes are built.
+#[derive(Debug)]
+pub struct Resource {
+    pub path: String,
+    collection: Mutex<Collection>,
+}
+
+impl Resource {
+    /// Creates a collection from a JSON array of objects, `None` starts it empty
+    pub fn new(path: &str, seed: Option<&str>) -> Result<Resource, ResourceError> {
+        let error = |error: String| ResourceError {
+            error: format!("Resource {path}: {error}"),
+        };
+        let items = match seed {
+            Some(seed) => serde_json::from_str::<Vec<Item>>(seed)
+                .map_err(|e| error(format!("seed must be an array of objects, {e}")))?,
+            None => Vec::new(),
+        };
+        let next_id = items
+            .iter()
+            .filter_map(|item| item.get("id").and_then(Value::as_u64))
+            .max()
+            .unwrap_or(0)
+            + 1;
+        Ok(Resource {
+            path: path.trim_end_matches('/').to_string(),
+            collection: Mutex::new(Collection { items, next_id }),
+        })
+    }
+
+    /// Creates a collection seeded from a JSON file
+    pub fn from_seed_file(path: &str, seed: &Path) -> Result<Resource, ResourceError> {
+        let seed = fs::read_to_string(seed).map_err(|e| ResourceError {
+            error: format!("{}: {}", seed.display(), e),
+        })?;
+        Resource::new(path, Some(&seed))
+    }
+
+    /// Routes answering the collection and its items, all sharing this resource
+    pub fn into_routes(self) -> Vec<Route> {
+        let resource = Arc::new(self);
+        let item_path = format!("{}/:{}", resource.path, ID_PARAM);
+        let collection_path = match resource.path.is_empty() {
+            true => "/".to_string(),
+            false => resource.path.clone(),
+        };
+        let content = EndpointContent::from_content("", &ResponseFormat::Json);
+        [
+            (Method::GET, &collection_path),
+            (Method::POST, &collection_path),
+            (Method::GET, &item_path),
+            (Method::PUT, &item_path),
+            (Method::PATCH, &item_path),
+            (Method::DELETE, &item_path),
+        ]
+        .into_iter()
+        .map(|(method, path)| {
+            Route::new(Some(method), path, content.clone()).with_resource(Arc::clone(&resource))
+        })
+        .collect()
+    }
+
+    /// Answers a request routed to this resource with the parameters its route captured
+    pub fn respond(&self, request: &Request, params: &IndexMap<String, String>) -> Response {
+        let id = params.get(ID_PARAM).map(String::as_str);
+        let mut collection = self
+            .collection
+            .lock()
+            .expect("resource lock is not poisoned");
+        match (request.method, id) {
+            (Method::GET | Method::HEAD, None) => json(Status::OK, &collection.items),
+            (Method::POST, None) => {
+                let mut item = match parse_item(request) {
+                    Ok(item) => item,
+                    Err(response) => return response,
+                };
+                let id = match item.get("id") {
+                    Some(id) => id_text(id),
+                    None => {
+                        // Skip ids taken by items that were given one as text
+                        while collection
+                            .position(&collection.next_id.to_string())
+                            .is_some()
+                        {
+                            collection.next_id += 1;
+                        }
+                        let id = collection.next_id;
+                        item.insert("id".to_string(), Value::from(id));
+                        id.to_string()
+                    }
+                };
+                if collection.position(&id).is_some() {
+                    return error(Status::CONFLICT, &format!("Item {id} already exists"));
+                }
+                if let Some(numeric) = item.get("id").and_then(Value::as_u64) {
+                    collection.next_id = collection.next_id.max(numeric + 1);
+                }
+                let location = format!("{}/{}", self.path, id);
+                collection.items.push(item.clone());
+                json(Status::CREATED, &item).header("Location", &location)
+            }
+            (method, Some(id)) => {
+                let Some(position) = collection.position(id) else {
+                    return error(Status::NOT_FOUND, &format!("Item {id} not found"));
+                };
+                match method {
+                    Method::GET | Method::HEAD => json(Status::OK, &collection.items[position]),
+                    Method::DELETE => {
+                        collection.items.remove(position);
+                        Response::empty().status(Status::NO_CONTENT)
+                    }
+                    Method::PUT | Method::PATCH => {
+                        let update = match parse_item(request) {
+                            Ok(update) => update,
+                            Err(response) => return response,
+                        };
+                        let item = &mut collection.items[position];
+                        let id = item.get("id").cloned().unwrap_or(Value::Null);
+                        match method {
+                            Method::PUT => *item = update,
+                            // Merge patch, null removes a field
+                            _ => update.into_iter().for_each(|(key, value)| match value {
+                                Value::Null => {
+                                    item.remove(&key);
+                                }
+                                value => {
+                                    item.insert(key, value);
+                                }
+                            }),
+                        }
+                        // The path decides which item this is
+                        item.insert("id".to_string(), id);
+                        json(Status::OK, item)
+                    }
+                    _ => Response::empty().status(Status::NOT_ALLOWED),
+                }
+            }
+            _ => Response::empty().status(Status::NOT_ALLOWED),
+        }
+    }
+}
+
+fn parse_item(request: &Request) -> Result<Item, Response> {
+    serde_json::from_slice::<Item>(&request.body).map_err(|e| {
+        error(
+            Status::BAD_REQUEST,
+            &format!("Body must be a JSON object, {e}"),
+        )
+    })
+}
+
+fn json(status: Status, value: &impl serde::Serialize) -> Response {
+    let body = serde_json::to_string_pretty(value).unwrap_or_default();
+    Response::content(body, &ResponseFormat::Json.to_string()).status(status)
+}
+
+fn error(status: Status, message: &str) -> Response {
+    json(status, &serde_json::json!({ "error": message }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::Headers;
+
+    fn request(method: Method, body: &str) -> Request {
+        Request {
+            method,
+            path: String::new(),
+            query: None,
+            version: "HTTP/1.1".to_string(),
+            headers: Headers::new(),
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    fn send(resource: &Resource, method: Method, id: Option<&str>, body: &str) -> Response {
+        let params = id
+            .map(|id| IndexMap::from([(ID_PARAM.to_string(), id.to_string())]))
+            .unwrap_or_default();
+        resource.respond(&request(method, body), &params)
+    }
+
+    #[test]
+    fn answers_crud_requests_with_their_status_codes() {
+        let users = Resource::new("/users", Some(r#"[{"id": 1, "name": "Ada"}]"#)).unwrap();
+        let created = send(&users, Method::POST, None, r#"{"name": "Alan"}"#);
+        assert_eq!(created.status, Status::CREATED);
+        assert_eq!(created.headers.get("Location"), Some("/users/2"));
+        assert_eq!(send(&users, Method::GET, None, "").status, Status::OK);
+        assert_eq!(send(&users, Method::GET, Some("2"), "").status, Status::OK);
+        assert_eq!(
+            send(&users, Method::POST, None, r#"{"id": 1}"#).status,
+            Status::CONFLICT
+        );
+        assert_eq!(
+            send(&users, Method::POST, None, "[]").status,
+            Status::BAD_REQUEST
+        );
+        assert_eq!(
+            send(&users, Method::PATCH, Some("1"), r#"{"name": null}"#).body_text(),
+            "{\n  \"id\": 1\n}"
+        );
+        assert_eq!(
+            send(&users, Method::DELETE, Some("1"), "").status,
+            Status::NO_CONTENT
+        );
+        assert_eq!(
+            send(&users, Method::PUT, Some("1"), "{}").status,
+            Status::NOT_FOUND
+        );
+    }
+
+    #[test]
+    fn assigns_ids_not_taken_by_text_ids() {
+        let users = Resource::new("/users", Some(r#"[{"id": "1"}]"#)).unwrap();
+        let created = send(&users, Method::POST, None, "{}");
+        assert_eq!(created.status, Status::CREATED);
+        assert_eq!(created.headers.get("Location"), Some("/users/2"));
+
+        send(&users, Method::POST, None, r#"{"id": "3"}"#);
+        let created = send(&users, Method::POST, None, "{}");
+        assert_eq!(created.headers.get("Location"), Some("/users/4"));
+    }
+}
@@ -0,0 +1,499 @@
+use crate::http::{percent_decode, Method, Request};
+use crate::matcher::Matcher;
+use crate::resource::Resource;
+use crate::scenario::{ScenarioStep, Scenarios, Sequence};
+use crate::EndpointContent;
+use indexmap::IndexMap;
+use std::{cmp::Reverse, fmt::Display, sync::Arc};
+
+/// One `/`-separated piece of a route path
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Static(String),
+    /// `:name`, matches exactly one segment
+    Param(String),
+    /// `*name`, matches the rest of the path
+    Wildcard(String),
+}
+
+impl Segment {
+    /// Lower ranks are more specific and win over higher ones
+    fn rank(&self) -> u8 {
+        match self {
+            Segment::Static(_) => 0,
+            Segment::Param(_) => 1,
+            Segment::Wildcard(_) => 2,
+        }
+    }
+}
+
+fn parse_segments(path: &str) -> Vec<Segment> {
+    path.split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| match segment.chars().next() {
+            Some(':') => Segment::Param(segment[1..].to_string()),
+            Some('*') => Segment::Wildcard(segment[1..].to_string()),
+            _ => Segment::Static(segment.to_string()),
+        })
+        .collect()
+}
+
+/// Endpoint served for a path pattern, optionally restricted to one method
+#[derive(Debug, Clone)]
+pub struct Route {
+    /// `None` serves the content on GET and echoes the body on POST
+    pub method: Option<Method>,
+    /// Path pattern, `:name` captures a segment and `*name` the remainder
+    pub path: String,
+    /// Query parameters the request must carry, `*` accepts any value
+    pub query: IndexMap<String, String>,
+    pub response: EndpointContent,
+    /// Collection answering the request in place of `response`
+    pub resource: Option<Arc<Resource>>,
+    /// Tried in order before falling back to `response`
+    pub matchers: Vec<Matcher>,
+    /// Served in place of `response`, one step per request
+    pub sequence: Option<Arc<Sequence>>,
+    pub scenario: Option<ScenarioStep>,
+    segments: Vec<Segment>,
+}
+
+/// A route picked for a request, with the path parameters it captured
+#[derive(Debug, Clone)]
+pub struct RouteMatch<'a> {
+    pub route: &'a Route,
+    pub params: IndexMap<String, String>,
+}
+
+impl Route {
+    pub fn new(method: Option<Method>, path: &str, response: EndpointContent) -> Self {
+        Route {
+            method,
+            path: path.to_string(),
+            query: IndexMap::new(),
+            response,
+            resource: None,
+            matchers: Vec::new(),
+            sequence: None,
+            scenario: None,
+            segments: parse_segments(path),
+        }
+    }
+
+    pub fn with_query(self, query: IndexMap<String, String>) -> Self {
+        Route { query, ..self }
+    }
+
+    pub fn with_resource(self, resource: Arc<Resource>) -> Self {
+        Route {
+            resource: Some(resource),
+            ..self
+        }
+    }
+
+    pub fn with_matchers(self, matchers: Vec<Matcher>) -> Self {
+        Route { matchers, ..self }
+    }
+
+    pub fn with_sequence(self, sequence: Sequence) -> Self {
+        Route {
+            sequence: Some(Arc::new(sequence)),
+            ..self
+        }
+    }
+
+    pub fn with_scenario(self, scenario: ScenarioStep) -> Self {
+        Route {
+            scenario: Some(scenario),
+            ..self
+        }
+    }
+
+    /// Response served when no matcher picks one, taking the next step of a sequence
+    pub fn next_response(&self) -> (&EndpointContent, Option<usize>) {
+        match &self.sequence {
+            Some(sequence) => {
+                let (index, response) = sequence.next();
+                (response, Some(index))
+            }
+            None => (&self.response, None),
+        }
+    }
+
+    fn required_state(&self) -> Option<&str> {
+        self.scenario
+            .as_ref()
+            .and_then(|scenario| scenario.required_state.as_deref())
+    }
+
+    /// First matcher accepting `request`, with its position
+    pub fn find_matcher(&self, request: &Request) -> Option<(usize, &Matcher)> {
+        self.matchers
+            .iter()
+            .enumerate()
+            .find(|(_, matcher)| matcher.matches(request))
+    }
+
+    fn accepts(&self, method: Method) -> bool {
+        match self.method {
+            Some(route_method) => route_method == method,
+            None => matches!(method, Method::GET | Method::POST),
+        }
+    }
+
+    /// Matches the path pattern, returning the captured parameters
+    fn capture(&self, path: &str) -> Option<IndexMap<String, String>> {
+        let parts = path
+            .split('/')
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>();
+        let mut params = IndexMap::new();
+        for (index, segment) in self.segments.iter().enumerate() {
+            match segment {
+                Segment::Static(expected) => {
+                    if parts.get(index) != Some(&expected.as_str()) {
+                        return None;
+                    }
+                }
+                Segment::Param(name) => {
+                    params.insert(name.clone(), percent_decode(parts.get(index)?));
+                }
+                Segment::Wildcard(name) => {
+                    params.insert(name.clone(), percent_decode(&parts[index..].join("/")));
+                    return Some(params);
+                }
+            }
+        }
+        (parts.len() == self.segments.len()).then_some(params)
+    }
+
+    fn matches_query(&self, query: &[(String, String)]) -> bool {
+        self.query.iter().all(|(name, expected)| {
+            query
+                .iter()
+                .any(|(key, value)| key == name && (expected == "*" || value == expected))
+        })
+    }
+
+    /// Ordering key, smaller is more specific
+    fn specificity(&self) -> (Vec<u8>, Reverse<usize>, bool, bool) {
+        (
+            self.segments.iter().map(Segment::rank).collect(),
+            Reverse(self.query.len()),
+            self.required_state().is_none(),
+            self.method.is_none(),
+        )
+    }
+}
+
+impl Display for Route {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.method {
+            Some(method) => write!(f, "{} {}", method, self.path)?,
+            None => write!(f, "{}", self.path)?,
+        }
+        for (index, (name, value)) in self.query.iter().enumerate() {
+            let separator = if index == 0 { '?' } else { '&' };
+            write!(f, "{separator}{name}={value}")?;
+        }
+        if let Some(ScenarioStep {
+            scenario,
+            required_state: Some(state),
+            ..
+        }) = &self.scenario
+        {
+            write!(f, " [{scenario}: {state}]")?;
+        }
+        Ok(())
+    }
+}
+
+/// Routes consulted by the server, in declaration order
+#[derive(Debug, Clone, Default)]
+pub struct RouteTable {
+    routes: Vec<Route>,
+    scenarios: Arc<Scenarios>,
+}
+
+impl RouteTable {
+    pub fn new() -> Self {
+        RouteTable::default()
+    }
+
+    /// Adds a route, replacing an earlier one with the same method, path, query and required
+    /// scenario state
+    pub fn insert(&mut self, route: Route) {
+        match self.routes.iter_mut().find(|existing| {
+            existing.method == route.method
+                && existing.path == route.path
+                && existing.query == route.query
+                && existing
+                    .scenario
+                    .as_ref()
+                    .map(|s| (&s.scenario, &s.required_state))
+                    == route
+                        .scenario
+                        .as_ref()
+                        .map(|s| (&s.scenario, &s.required_state))
+        }) {
+            Some(existing) => *existing = route,
+            None => self.routes.push(route),
+        }
+    }
+
+    pub fn extend(&mut self, routes: impl IntoIterator<Item = Route>) {
+        routes.into_iter().for_each(|route| self.insert(route));
+    }
+
+    /// Takes out the routes `matches` picks, returning them
+    pub fn remove(&mut self, matches: impl Fn(&Route) -> bool) -> Vec<Route> {
+        let (removed, kept) = self.routes.drain(..).partition(|route| matches(route));
+        self.routes = kept;
+        removed
+    }
+
+    /// Looks up the route for a request.
+    ///
+    /// Static segments win over `:params`, which win over `*wildcards`; then routes with more
+    /// query conditions and routes declaring the method win, and finally declaration order.
+    pub fn find(
+        &self,
+        method: Method,
+        path: &str,
+        query: &[(String, String)],
+    ) -> Option<RouteMatch<'_>> {
+        self.routes
+            .iter()
+            .filter(|route| route.accepts(method) && route.matches_query(query))
+            .filter(|route| self.in_required_state(route))
+            .filter_map(|route| {
+                route
+                    .capture(path)
+                    .map(|params| RouteMatch { route, params })
+            })
+            .min_by_key(|found| found.route.specificity())
+    }
+
+    /// Methods answered on `path`, including the HEAD and OPTIONS the server derives. None when
+    /// no route serves the path for this query in the current scenario states.
+    pub fn allowed_methods(&self, path: &str, query: &[(String, String)]) -> Vec<Method> {
+        let explicit = |method| self.find(method, path, query).is_some();
+        if !self.routes.iter().any(|route| {
+            route.matches_query(query)
+                && self.in_required_state(route)
+                && route.capture(path).is_some()
+        }) {
+            return vec![];
+        }
+        Method::ALL
+            .into_iter()
+            .filter(|method| match method {
+                Method::HEAD => explicit(Method::HEAD) || explicit(Method::GET),
+                Method::OPTIONS => true,
+                method => explicit(*method),
+            })
+            .collect()
+    }
+
+    fn in_required_state(&self, route: &Route) -> bool {
+        match (&route.scenario, route.required_state()) {
+            (Some(step), Some(state)) => self.scenarios.state(&step.scenario) == state,
+            _ => true,
+        }
+    }
+
+    /// Moves the scenario of `route` on, after it answered a request
+    pub fn advance(&self, route: &Route) {
+        if let Some(ScenarioStep {
+            scenario,
+            new_state: Some(state),
+            ..
+        }) = &route.scenario
+        {
+            self.scenarios.set_state(scenario, state);
+        }
+    }
+
+    /// Current state of every scenario the routes take part in
+    pub fn scenario_states(&self) -> Vec<(String, String)> {
+        let mut names = self
+            .routes
+            .iter()
+            .filter_map(|route| route.scenario.as_ref())
+            .map(|step| step.scenario.clone())
+            .collect::<Vec<_>>();
+        names.sort();
+        names.dedup();
+        names
+            .into_iter()
+            .map(|name| {
+                let state = self.scenarios.state(&name);
+                (name, state)
+            })
+            .collect()
+    }
+
+    /// Puts every scenario back in its first state and every sequence on its first step
+    pub fn reset(&self) {
+        self.scenarios.reset();
+        self.routes
+            .iter()
+            .filter_map(|route| route.sequence.as_ref())
+            .for_each(|sequence| sequence.reset());
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Route> {
+        self.routes.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.routes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty()
+    }
+}
+
+impl IntoIterator for RouteTable {
+    type Item = Route;
+    type IntoIter = std::vec::IntoIter<Route>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.routes.into_iter()
+    }
+}
+
+impl FromIterator<Route> for RouteTable {
+    fn from_iter<I: IntoIterator<Item = Route>>(iter: I) -> Self {
+        let mut table = RouteTable::new();
+        table.extend(iter);
+        table
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(method: Option<Method>, path: &str) -> Route {
+        Route::new(method, path, EndpointContent::default())
+    }
+
+    fn query(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn found(table: &RouteTable, method: Method, path: &str, query: &[(String, String)]) -> String {
+        table
+            .find(method, path, query)
+            .map(|found| found.route.to_string())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn captures_params_and_wildcards() {
+        let params = route(None, "/users/:id/posts/:post")
+            .capture("/users/7/posts/hello%20world")
+            .unwrap();
+        assert_eq!(params["id"], "7");
+        assert_eq!(params["post"], "hello world");
+
+        let params = route(None, "/files/*rest")
+            .capture("/files/a/b/c.txt")
+            .unwrap();
+        assert_eq!(params["rest"], "a/b/c.txt");
+
+        assert!(route(None, "/users/:id").capture("/users").is_none());
+        assert!(route(None, "/users/:id")
+            .capture("/users/7/posts")
+            .is_none());
+        assert!(route(None, "/users").capture("/accounts").is_none());
+    }
+
+    #[test]
+    fn prefers_static_over_params_over_wildcards() {
+        let table = [
+            route(None, "/users/*rest"),
+            route(None, "/users/:id"),
+            route(None, "/users/me"),
+        ]
+        .into_iter()
+        .collect::<RouteTable>();
+        assert_eq!(found(&table, Method::GET, "/users/me", &[]), "/users/me");
+        assert_eq!(found(&table, Method::GET, "/users/7", &[]), "/users/:id");
+        assert_eq!(
+            found(&table, Method::GET, "/users/7/posts", &[]),
+            "/users/*rest"
+        );
+    }
+
+    #[test]
+    fn prefers_query_conditions_then_methods_then_declaration_order() {
+        let with_query =
+            route(None, "/search").with_query(IndexMap::from([("q".to_string(), "*".to_string())]));
+        let table = [
+            route(None, "/search"),
+            route(Some(Method::GET), "/search"),
+            with_query,
+        ]
+        .into_iter()
+        .collect::<RouteTable>();
+        assert_eq!(
+            found(&table, Method::GET, "/search", &query(&[("q", "x")])),
+            "/search?q=*"
+        );
+        assert_eq!(found(&table, Method::GET, "/search", &[]), "GET /search");
+        assert_eq!(found(&table, Method::POST, "/search", &[]), "/search");
+        assert!(table.find(Method::DELETE, "/search", &[]).is_none());
+    }
+
+    #[test]
+    fn lists_allowed_methods_for_a_path() {
+        let table = [
+            route(Some(Method::GET), "/items/:id"),
+            route(Some(Method::DELETE), "/items/:id"),
+        ]
+        .into_iter()
+        .collect::<RouteTable>();
+        assert_eq!(
+            table.allowed_methods("/items/1", &[]),
+            [Method::GET, Method::HEAD, Method::DELETE, Method::OPTIONS]
+        );
+        assert!(table.allowed_methods("/other", &[]).is_empty());
+    }
+
+    #[test]
+    fn lists_no_methods_when_the_query_does_not_match() {
+        let table = [route(Some(Method::GET), "/search")
+            .with_query(IndexMap::from([("q".to_string(), "*".to_string())]))]
+        .into_iter()
+        .collect::<RouteTable>();
+        assert_eq!(
+            table.allowed_methods("/search", &query(&[("q", "x")])),
+            [Method::GET, Method::HEAD, Method::OPTIONS]
+        );
+        assert!(table.allowed_methods("/search", &[]).is_empty());
+    }
+
+    #[test]
+    fn lists_no_methods_outside_the_required_scenario_state() {
+        let step = |required_state: Option<&str>, new_state: Option<&str>| ScenarioStep {
+            scenario: "checkout".to_string(),
+            required_state: required_state.map(str::to_string),
+            new_state: new_state.map(str::to_string),
+        };
+        let pay = route(Some(Method::POST), "/pay").with_scenario(step(None, Some("Paid")));
+        let table = [route(Some(Method::GET), "/receipt").with_scenario(step(Some("Paid"), None))]
+            .into_iter()
+            .collect::<RouteTable>();
+        assert!(table.allowed_methods("/receipt", &[]).is_empty());
+        table.advance(&pay);
+        assert_eq!(
+            table.allowed_methods("/receipt", &[]),
+            [Method::GET, Method::HEAD, Method::OPTIONS]
+        );
+    }
+}
@@ -0,0 +1,97 @@
+use crate::EndpointContent;
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+/// State every scenario starts in, and returns to on reset
+pub const STARTED: &str = "Started";
+
+/// What a sequence serves once every step has been served
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SequenceMode {
+    /// Keep serving the last step
+    #[default]
+    Stick,
+    /// Start over from the first step
+    Cycle,
+}
+
+/// Responses served one after the other by a route
+#[derive(Debug)]
+pub struct Sequence {
+    pub steps: Vec<EndpointContent>,
+    pub mode: SequenceMode,
+    served: AtomicUsize,
+}
+
+impl Sequence {
+    /// `steps` must not be empty
+    pub fn new(steps: Vec<EndpointContent>, mode: SequenceMode) -> Self {
+        Sequence {
+            steps,
+            mode,
+            served: AtomicUsize::new(0),
+        }
+    }
+
+    /// Takes the step for this call, with its position
+    pub fn next(&self) -> (usize, &EndpointContent) {
+        let served = self.served.fetch_add(1, Ordering::SeqCst);
+        let index = match self.mode {
+            SequenceMode::Stick => served.min(self.steps.len() - 1),
+            SequenceMode::Cycle => served % self.steps.len(),
+        };
+        (index, &self.steps[index])
+    }
+
+    pub fn reset(&self) {
+        self.served.store(0, Ordering::SeqCst);
+    }
+}
+
+/// Ties a route to a named scenario, like the scenarios of WireMock
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScenarioStep {
+    pub scenario: String,
+    /// The route only answers while the scenario is in this state
+    pub required_state: Option<String>,
+    /// State the scenario moves to once the route answered
+    pub new_state: Option<String>,
+}
+
+/// Current state of every scenario, the ones not listed are in [`STARTED`]
+#[derive(Debug, Default)]
+pub struct Scenarios {
+    states: Mutex<HashMap<String, String>>,
+}
+
+impl Scenarios {
+    pub fn state(&self, scenario: &str) -> String {
+        self.states
+            .lock()
+            .expect("scenario lock is not poisoned")
+            .get(scenario)
+            .cloned()
+            .unwrap_or_else(|| STARTED.to_string())
+    }
+
+    pub fn set_state(&self, scenario: &str, state: &str) {
+        self.states
+            .lock()
+            .expect("scenario lock is not poisoned")
+            .insert(scenario.to_string(), state.to_string());
+    }
+
+    pub fn reset(&self) {
+        self.states
+            .lock()
+            .expect("scenario lock is not poisoned")
+            .clear();
+    }
+}
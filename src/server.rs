@@ -1,14 +1,82 @@
-use crate::EndpointContent;
 use crate::Message;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
 use indexmap::IndexMap;
-use nanohttp::{Method, Request as HttpRequest, Response as HttpResponse, Status};
+use itertools::Itertools;
 use std::net::SocketAddr;
 use std::sync::Arc;
-use testsuite::ResponseMessage;
-use tokio::io::AsyncBufReadExt;
-use tokio::sync::mpsc;
-use tokio::{io::AsyncWriteExt, io::BufReader, net::tcp::OwnedReadHalf, net::TcpStream};
+use std::time::Duration;
+use testsuite::admin::Admin;
+use testsuite::chaos::{Chaos, Fault, Injection};
+use testsuite::http::{
+    read_request, Method, Request as HttpRequest, Response as HttpResponse, Status,
+};
+use testsuite::journal::Journal;
+use testsuite::proxy::Proxy;
+use testsuite::routes::{Route, RouteMatch, RouteTable};
+use testsuite::tls::TlsInfo;
+use testsuite::ConnectionId;
+use testsuite::{Limits, ResponseMessage};
+use tokio::io::{split, AsyncRead, AsyncWrite};
+use tokio::sync::{mpsc, watch};
+use tokio::time::{sleep, timeout};
+use tokio::{io::AsyncWriteExt, io::BufReader, net::TcpStream};
+use tokio_rustls::{server::TlsStream, TlsAcceptor};
+
+/// Settings shared by every connection
+pub struct Settings {
+    pub limits: Limits,
+    pub proxy: Option<Proxy>,
+    /// Latency and faults for responses that do not set their own
+    pub chaos: Chaos,
+    /// Every exchange served, for the admin API
+    pub journal: Arc<Journal>,
+    pub admin: Option<Admin>,
+}
+
+/// Client end of a connection
+#[derive(Debug, Clone, Copy)]
+struct Peer {
+    addr: SocketAddr,
+    connection: ConnectionId,
+    /// Whether the connection is https
+    secure: bool,
+}
+
+/// Response picked for a request
+struct Served {
+    response: HttpResponse,
+    /// What picked the response, for routes with a choice of responses
+    picked: Option<String>,
+    chaos: Chaos,
+}
+
+impl Served {
+    fn new(response: HttpResponse) -> Self {
+        Served {
+            response,
+            picked: None,
+            chaos: Chaos::default(),
+        }
+    }
+}
+
+/// Stream a connection is served over, plain or TLS
+trait Connection: AsyncRead + AsyncWrite + Unpin {
+    fn tcp(&self) -> &TcpStream;
+}
+
+impl Connection for TcpStream {
+    fn tcp(&self) -> &TcpStream {
+        self
+    }
+}
+
+impl Connection for TlsStream<TcpStream> {
+    fn tcp(&self) -> &TcpStream {
+        self.get_ref().0
+    }
+}
 
 pub async fn push_message(tx: mpsc::Sender<Message>, message: Message) {
     tx.send(message).await.unwrap();
@@ -16,114 +84,336 @@ pub async fn push_message(tx: mpsc::Sender<Message>, message: Message) {
 
 async fn handle(
     req: HttpRequest,
-    map: &Arc<IndexMap<String, EndpointContent>>,
-    addr: SocketAddr,
+    received: DateTime<Utc>,
+    routes: &Arc<RouteTable>,
+    settings: &Settings,
+    peer: Peer,
     sender: mpsc::Sender<Message>,
-) -> HttpResponse {
+) -> (HttpResponse, Injection) {
+    // Admin requests are not mocked, recorded or shown
+    if let Some(admin) = settings
+        .admin
+        .as_ref()
+        .filter(|admin| admin.serves(&req.path))
+    {
+        return (admin.handle(&req).await, Injection::default());
+    }
+    // Paths with local routes get the derived HEAD, preflight and 405 answers, not the upstream's
+    let unmatched = routes
+        .allowed_methods(&req.path, &req.query_params())
+        .is_empty();
+    let served = match &settings.proxy {
+        Some(proxy) if unmatched => Served::new(proxy.forward(&req).await),
+        _ => respond(&req, routes),
+    };
+    let injection = served.chaos.or(&settings.chaos).inject();
+    let response = match injection.fault {
+        Some(Fault::Error) => HttpResponse::content("Injected fault", "text/plain")
+            .status(Status::INTERNAL_SERVER_ERROR),
+        _ => served.response,
+    };
+    let method = req.method;
+    let message = ResponseMessage::new(
+        peer.addr,
+        peer.connection,
+        req,
+        received,
+        &response,
+        Some(method),
+        Some(response.status),
+    )
+    .with_matcher(served.picked)
+    .with_injected((!injection.is_empty()).then(|| injection.to_string()))
+    .with_timing(
+        peer.secure,
+        (Utc::now() - received).to_std().unwrap_or_default() + injection.delay.unwrap_or_default(),
+    );
+    settings.journal.record(&message);
+    push_message(sender, Message::Response(Box::new(message))).await;
+    (response, injection)
+}
+
+fn respond(req: &HttpRequest, routes: &RouteTable) -> Served {
+    let query = req.query_params();
+    if let Some(RouteMatch { route, params }) = routes.find(req.method, &req.path, &query) {
+        // Routes without a method echo posted bodies back, unless a matcher picks a response
+        let served = match (route.method, req.method) {
+            (None, Method::POST) if route.find_matcher(req).is_none() => Served {
+                response: HttpResponse::content(req.body.clone(), &route.response.format)
+                    .status(Status::OK),
+                picked: None,
+                chaos: route.response.chaos.clone(),
+            },
+            _ => serve(route, req, &params),
+        };
+        routes.advance(route);
+        return served;
+    }
+
+    let allowed = routes.allowed_methods(&req.path, &query);
+    if allowed.is_empty() {
+        return Served::new(HttpResponse::empty().status(Status::NOT_FOUND));
+    }
+    let allow = allowed.iter().map(Method::to_string).join(", ");
     match req.method {
-        Method::GET => {
-            if let Some(data) = map.get(req.path.uri.as_str()) {
-                let format = data.format.to_string();
-                if let Some(content) = &data.content {
-                    let response = HttpResponse::content(content, &format).status(Status::Ok);
-                    push_message(
-                        sender,
-                        Message::Response(ResponseMessage::new(
-                            addr,
-                            &response,
-                            Some(Method::GET),
-                            Some(Status::Ok),
-                        )),
-                    )
-                    .await;
-                    response
-                } else {
-                    let response = HttpResponse::empty().status(Status::Ok);
-                    push_message(
-                        sender,
-                        Message::Response(ResponseMessage::new(
-                            addr,
-                            &response,
-                            Some(Method::GET),
-                            Some(Status::Ok),
-                        )),
+        Method::HEAD if allowed.contains(&Method::GET) => routes
+            .find(Method::GET, &req.path, &query)
+            .map(|found| {
+                let served = serve(found.route, req, &found.params);
+                Served {
+                    response: served.response.without_body(),
+                    ..served
+                }
+            })
+            .unwrap_or_else(|| Served::new(HttpResponse::empty().status(Status::NOT_FOUND))),
+        Method::OPTIONS => Served::new(preflight(req, &allow)),
+        _ => Served::new(
+            HttpResponse::empty()
+                .status(Status::NOT_ALLOWED)
+                .header("Allow", &allow),
+        ),
+    }
+}
+
+/// Builds the response of a matched route, describing what picked it when the route has a
+/// choice of responses
+fn serve(route: &Route, req: &HttpRequest, params: &IndexMap<String, String>) -> Served {
+    if let Some(resource) = &route.resource {
+        return Served {
+            chaos: route.response.chaos.clone(),
+            ..Served::new(resource.respond(req, params))
+        };
+    }
+    if let Some((index, matcher)) = route.find_matcher(req) {
+        return Served {
+            response: matcher.response.respond_to(req, params),
+            picked: Some(format!("matcher {}: {}", index + 1, matcher)),
+            chaos: matcher.response.chaos.clone(),
+        };
+    }
+    let (content, step) = route.next_response();
+    let picked = match (step, &route.sequence) {
+        (Some(step), Some(sequence)) => {
+            Some(format!("step {} of {}", step + 1, sequence.steps.len()))
+        }
+        _ if !route.matchers.is_empty() => Some("fallback".to_string()),
+        _ => None,
+    };
+    Served {
+        response: content.respond_to(req, params),
+        picked,
+        chaos: content.chaos.clone(),
+    }
+}
+
+/// Answers OPTIONS, including CORS preflight requests, for paths without an OPTIONS route
+fn preflight(req: &HttpRequest, allow: &str) -> HttpResponse {
+    let response = HttpResponse::empty()
+        .status(Status::NO_CONTENT)
+        .header("Allow", allow);
+    match (
+        req.headers.get("Origin"),
+        req.headers.get("Access-Control-Request-Method"),
+    ) {
+        (Some(origin), Some(_)) => {
+            let response = response
+                .header("Access-Control-Allow-Origin", origin)
+                .header("Access-Control-Allow-Methods", allow)
+                .header("Access-Control-Max-Age", "86400")
+                .header("Vary", "Origin");
+            match req.headers.get("Access-Control-Request-Headers") {
+                Some(headers) => response.header("Access-Control-Allow-Headers", headers),
+                None => response,
+            }
+        }
+        _ => response,
+    }
+}
+
+pub async fn handle_connection(
+    addr: SocketAddr,
+    connection: ConnectionId,
+    stream: TcpStream,
+    tls: Option<&TlsAcceptor>,
+    routes: &watch::Receiver<Arc<RouteTable>>,
+    sender: tokio::sync::mpsc::Sender<Message>,
+    settings: &Settings,
+) -> Result<()> {
+    let Some(acceptor) = tls else {
+        push_message(
+            sender.clone(),
+            Message::ConnectionReceived(Some(addr), connection, None),
+        )
+        .await;
+        let peer = Peer {
+            addr,
+            connection,
+            secure: false,
+        };
+        return serve_connection(peer, stream, routes, sender, settings).await;
+    };
+
+    let wait = settings.limits.read_timeout();
+    let stream = timeout(wait, acceptor.accept(stream))
+        .await
+        .map_err(|_| anyhow!("Timed out after {:?} waiting for TLS handshake", wait))?
+        .map_err(|e| anyhow!("TLS handshake failed: {}", e))?;
+    let info = TlsInfo::from_connection(stream.get_ref().1);
+    push_message(
+        sender.clone(),
+        Message::ConnectionReceived(Some(addr), connection, Some(info)),
+    )
+    .await;
+    let peer = Peer {
+        addr,
+        connection,
+        secure: true,
+    };
+    serve_connection(peer, stream, routes, sender, settings).await
+}
+
+async fn serve_connection<S: Connection>(
+    peer: Peer,
+    stream: S,
+    routes: &watch::Receiver<Arc<RouteTable>>,
+    sender: tokio::sync::mpsc::Sender<Message>,
+    settings: &Settings,
+) -> Result<()> {
+    let limits = &settings.limits;
+    let (read_half, mut write_half) = split(stream);
+    let mut reader = BufReader::new(read_half);
+    let mut served = 0;
+    loop {
+        // Between requests the client only gets the idle timeout to start the next one
+        let wait = match served {
+            0 => limits.read_timeout(),
+            _ => limits.keep_alive_timeout(),
+        };
+        let read = read_request(&mut reader, &mut write_half, limits.max_body_size);
+        let received = match timeout(wait, read).await {
+            Ok(received) => received,
+            Err(_) if served > 0 => return Ok(()),
+            Err(_) => return Err(anyhow!("Timed out after {:?} waiting for request", wait)),
+        };
+
+        let req = match received {
+            Ok(Some(req)) => req,
+            Ok(None) if served > 0 => return Ok(()),
+            Ok(None) => return Err(anyhow!("Connection closed before a request was sent")),
+            Err(err) => {
+                if let Some(status) = err.status() {
+                    let res = HttpResponse::content(err.to_string(), "text/plain")
+                        .status(status)
+                        .header("Connection", "close");
+                    let _ = timeout(
+                        limits.write_timeout(),
+                        write_half.write_all(&res.to_bytes()),
                     )
                     .await;
-                    response
                 }
-            } else {
-                let response = HttpResponse::empty().status(Status::NotFound);
-                push_message(
-                    sender,
-                    Message::Response(ResponseMessage::new(
-                        addr,
-                        &response,
-                        Some(Method::GET),
-                        Some(Status::NotFound),
-                    )),
-                )
-                .await;
-                response
+                return Err(err.into());
             }
+        };
+
+        let received = Utc::now();
+        let keep_alive = wants_keep_alive(&req);
+        // Each request sees the latest routes, even on a long lived connection
+        let table = Arc::clone(&routes.borrow());
+        let (mut res, injection) =
+            handle(req, received, &table, settings, peer, sender.clone()).await;
+        if keep_alive {
+            res.headers.set("Connection", "keep-alive");
+            res.headers.set(
+                "Keep-Alive",
+                &format!("timeout={}", limits.keep_alive_timeout),
+            );
+        } else {
+            res.headers.set("Connection", "close");
+        }
+
+        if let Some(delay) = injection.delay {
+            sleep(delay).await;
         }
-        Method::POST => {
-            if let Some(data) = map.get(req.path.uri.as_str()) {
-                let format = data.format.to_string();
-                let response = HttpResponse::content(&req.body, &format).status(Status::Ok);
-                push_message(
-                    sender,
-                    Message::Response(ResponseMessage::new(
-                        addr,
-                        &response,
-                        Some(Method::POST),
-                        Some(Status::Ok),
-                    )),
+        let bytes = res.to_bytes();
+        match injection.fault {
+            Some(Fault::Close) => return Ok(()),
+            Some(Fault::Malformed) => {
+                let _ = timeout(
+                    limits.write_timeout(),
+                    write_half.write_all(b"HTTP/1.1 ???\r\n\0\x7fnot http\r\n\r\n"),
                 )
                 .await;
-                response
-            } else {
-                let response = HttpResponse::empty().status(Status::NotFound);
-                push_message(
-                    sender,
-                    Message::Response(ResponseMessage::new(
-                        addr,
-                        &response,
-                        Some(Method::POST),
-                        Some(Status::NotFound),
-                    )),
+                return Ok(());
+            }
+            Some(Fault::Reset) => {
+                let _ = timeout(
+                    limits.write_timeout(),
+                    write_half.write_all(&bytes[..bytes.len() / 2]),
                 )
                 .await;
-                response
+                // Lingering for zero seconds makes the close send a RST
+                let stream = reader.into_inner().unsplit(write_half);
+                stream.tcp().set_linger(Some(Duration::ZERO))?;
+                return Ok(());
             }
+            Some(Fault::Error) | None => {}
+        }
+        write_response(
+            &mut write_half,
+            &bytes,
+            injection.bandwidth,
+            limits.write_timeout(),
+        )
+        .await?;
+
+        served += 1;
+        if !keep_alive {
+            return Ok(());
         }
-        _ => HttpResponse::empty().status(Status::NotAllowed),
     }
 }
 
-pub async fn handle_connection(
-    addr: SocketAddr,
-    stream: TcpStream,
-    map: &Arc<IndexMap<String, EndpointContent>>,
-    sender: tokio::sync::mpsc::Sender<Message>,
+/// Writes the response, throttled to `bandwidth` bytes per second when given
+async fn write_response<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    bytes: &[u8],
+    bandwidth: Option<u64>,
+    write_timeout: Duration,
 ) -> Result<()> {
-    push_message(sender.clone(), Message::ConnectionReceived(Some(addr))).await;
-
-    let (mut read_half, mut write_half) = stream.into_split();
-    let received = read_stream(&mut read_half).await?;
-
-    let req = HttpRequest::from_string(&received).unwrap();
-    let res = handle(req, map, addr, sender).await;
-    write_half.write_all(res.to_string().as_bytes()).await?;
-    write_half.flush().await.unwrap();
+    // Throttled responses go out in tenths of a second worth of bytes
+    let (chunk, pause) = match bandwidth {
+        Some(bandwidth) => {
+            let chunk = (bandwidth / 10).max(1);
+            (
+                chunk as usize,
+                Duration::from_secs_f64(chunk as f64 / bandwidth as f64),
+            )
+        }
+        None => (bytes.len().max(1), Duration::ZERO),
+    };
+    for (index, part) in bytes.chunks(chunk).enumerate() {
+        if index > 0 {
+            sleep(pause).await;
+        }
+        timeout(write_timeout, writer.write_all(part))
+            .await
+            .map_err(|_| anyhow!("Timed out after {:?} writing response", write_timeout))??;
+    }
+    timeout(write_timeout, writer.flush())
+        .await
+        .map_err(|_| anyhow!("Timed out after {:?} writing response", write_timeout))??;
     Ok(())
 }
 
-async fn read_stream(stream: &mut OwnedReadHalf) -> anyhow::Result<String> {
-    // 8kb internal buffer
-    let mut reader = BufReader::new(stream);
-    let received: Vec<u8> = reader.fill_buf().await?.to_vec();
-    //process?
-    //TODO: chunking of data if over 8kb
-    reader.consume(received.len());
-    Ok(String::from_utf8(received)?)
+/// HTTP/1.1 connections persist unless the client asks to close, HTTP/1.0 ones only on request
+fn wants_keep_alive(req: &HttpRequest) -> bool {
+    match req
+        .headers
+        .get("Connection")
+        .map(|value| value.to_ascii_lowercase())
+    {
+        Some(value) if value.contains("close") => false,
+        Some(value) if value.contains("keep-alive") => true,
+        _ => req.version == "HTTP/1.1",
+    }
 }
@@ -0,0 +1,190 @@
+use crate::http::{Headers, Method, Request, Response, Status};
+use crate::journal::Exchange;
+use crate::tls::TlsInfo;
+use crate::{ConnectionFailedError, ConnectionId, Message, ResponseMessage};
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use log::warn;
+use serde_json::{json, Value};
+use std::{collections::HashMap, fs, net::SocketAddr, path::Path, time::Duration};
+use tokio::{
+    fs::OpenOptions,
+    io::AsyncWriteExt,
+    sync::mpsc::{self, Receiver},
+};
+
+/// Appends the connections, failures and exchanges of `receiver` to the JSON lines file at
+/// `path`, passing every message on to the returned receiver.
+///
+/// Each run starts with a `run` line, as connection ids start over with every server run.
+pub async fn record(mut receiver: Receiver<Message>, path: &Path) -> Result<Receiver<Message>> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await
+        .with_context(|| format!("Could not open session log {}", path.display()))?;
+    let run = json!({
+        "event": "run",
+        "time": Utc::now().to_rfc3339(),
+        "version": env!("CARGO_PKG_VERSION"),
+    });
+    file.write_all(format!("{run}\n").as_bytes())
+        .await
+        .with_context(|| format!("Could not write to session log {}", path.display()))?;
+    let path = path.to_path_buf();
+    let (sender, forwarded) = mpsc::channel(100);
+    tokio::spawn(async move {
+        while let Some(message) = receiver.recv().await {
+            if let Some(entry) = entry(&message) {
+                let line = format!("{entry}\n");
+                if let Err(err) = file.write_all(line.as_bytes()).await {
+                    warn!("Could not write to {}: {}", path.display(), err);
+                }
+            }
+            if sender.send(message).await.is_err() {
+                break;
+            }
+        }
+    });
+    Ok(forwarded)
+}
+
+/// Line of the session log for `message`, events not tied to a client are left out
+fn entry(message: &Message) -> Option<Value> {
+    match message {
+        Message::ConnectionReceived(Some(addr), connection, tls) => Some(json!({
+            "event": "connection",
+            "time": Utc::now().to_rfc3339(),
+            "address": addr.to_string(),
+            "connection": connection,
+            "tls": tls,
+        })),
+        Message::ConnectionFailed(ConnectionFailedError::Parsing((addr, err))) => Some(json!({
+            "event": "error",
+            "time": Utc::now().to_rfc3339(),
+            "address": addr.to_string(),
+            "error": err.to_string(),
+        })),
+        Message::Response(message) => {
+            let mut exchange = Exchange::new(0, message).to_json();
+            if let Value::Object(object) = &mut exchange {
+                object.remove("id");
+                object.insert("event".to_string(), Value::from("exchange"));
+            }
+            Some(exchange)
+        }
+        _ => None,
+    }
+}
+
+/// Reads a session log back into the messages that produced it. Lines that cannot be read
+/// are skipped and counted.
+///
+/// Connections are numbered anew across the whole file so those of different runs are not
+/// grouped together.
+pub fn load(path: &Path) -> Result<(Vec<Message>, usize)> {
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("Could not read session log {}", path.display()))?;
+    let mut skipped = 0;
+    let mut run = 0;
+    let mut connections = HashMap::<(usize, ConnectionId), ConnectionId>::new();
+    let mut messages = vec![];
+    for line in text.lines().filter(|line| !line.trim().is_empty()) {
+        let entry = match serde_json::from_str::<Value>(line) {
+            Ok(entry) => entry,
+            Err(_) => {
+                skipped += 1;
+                continue;
+            }
+        };
+        if entry["event"] == "run" {
+            run += 1;
+            continue;
+        }
+        let mut message = match message(&entry) {
+            Ok(message) => message,
+            Err(_) => {
+                skipped += 1;
+                continue;
+            }
+        };
+        let mut renumber = |id: &mut ConnectionId| {
+            let next = connections.len() as ConnectionId + 1;
+            *id = *connections.entry((run, *id)).or_insert(next);
+        };
+        match &mut message {
+            Message::ConnectionReceived(_, id, _) => renumber(id),
+            Message::Response(response) => renumber(&mut response.connection),
+            _ => {}
+        }
+        messages.push(message);
+    }
+    Ok((messages, skipped))
+}
+
+fn message(entry: &Value) -> Result<Message> {
+    let text = |value: &Value| value.as_str().unwrap_or_default().to_string();
+    let address = text(&entry["address"]).parse::<SocketAddr>()?;
+    match entry["event"].as_str() {
+        Some("connection") => Ok(Message::ConnectionReceived(
+            Some(address),
+            entry["connection"].as_u64().unwrap_or_default() as ConnectionId,
+            serde_json::from_value::<Option<TlsInfo>>(entry["tls"].clone())?,
+        )),
+        Some("error") => Ok(Message::ConnectionFailed(ConnectionFailedError::Parsing((
+            address,
+            anyhow!(text(&entry["error"])),
+        )))),
+        Some("exchange") => {
+            let (request, response) = (&entry["request"], &entry["response"]);
+            let method = text(&request["method"]).parse::<Method>()?;
+            let status = Status(response["status"].as_u64().unwrap_or_default() as u16);
+            let request = Request {
+                method,
+                path: text(&request["path"]),
+                query: request["query"].as_str().map(str::to_string),
+                version: text(&request["version"]),
+                headers: headers(&request["headers"]),
+                body: text(&request["body"]).into_bytes(),
+            };
+            let response = Response {
+                version: "HTTP/1.1".to_string(),
+                status,
+                headers: headers(&response["headers"]),
+                body: text(&response["body"]).into_bytes(),
+            };
+            let received =
+                DateTime::parse_from_rfc3339(&text(&entry["received"]))?.with_timezone(&Utc);
+            let elapsed = entry["elapsed_ms"].as_f64().unwrap_or_default() / 1000.0;
+            Ok(Message::Response(Box::new(
+                ResponseMessage::new(
+                    address,
+                    entry["connection"].as_u64().unwrap_or_default() as ConnectionId,
+                    request,
+                    received,
+                    &response,
+                    Some(method),
+                    Some(status),
+                )
+                .with_matcher(entry["matcher"].as_str().map(str::to_string))
+                .with_injected(entry["injected"].as_str().map(str::to_string))
+                .with_timing(
+                    entry["secure"].as_bool().unwrap_or_default(),
+                    Duration::from_secs_f64(elapsed.max(0.0)),
+                ),
+            )))
+        }
+        _ => Err(anyhow!("Unknown session event: {}", entry["event"])),
+    }
+}
+
+fn headers(object: &Value) -> Headers {
+    let mut headers = Headers::new();
+    if let Some(object) = object.as_object() {
+        object
+            .iter()
+            .for_each(|(name, value)| headers.push(name, value.as_str().unwrap_or_default()));
+    }
+    headers
+}
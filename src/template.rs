@@ -0,0 +1,131 @@
+use crate::http::Request;
+use chrono::Utc;
+use indexmap::IndexMap;
+use rand::Rng;
+use serde_json::Value;
+
+/// Request data a template is rendered against
+#[derive(Debug, Clone, Copy)]
+pub struct TemplateContext<'a> {
+    pub request: &'a Request,
+    /// Parameters captured by the route's path pattern
+    pub params: &'a IndexMap<String, String>,
+}
+
+/// Replaces every `{{expression}}` in `template`.
+///
+/// Values missing from the request render as an empty string, expressions that are not
+/// understood are left in place so they stand out in the response.
+pub fn render(template: &str, context: &TemplateContext) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}").map(|end| start + end) else {
+            break;
+        };
+        rendered.push_str(&rest[..start]);
+        match evaluate(rest[start + 2..end].trim(), context) {
+            Some(value) => rendered.push_str(&value),
+            None => rendered.push_str(&rest[start..end + 2]),
+        }
+        rest = &rest[end + 2..];
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+fn evaluate(expression: &str, context: &TemplateContext) -> Option<String> {
+    let mut words = expression.split_whitespace();
+    let name = words.next()?;
+    let args = words.collect::<Vec<_>>();
+    match (name, args.as_slice()) {
+        ("now", []) => Some(Utc::now().to_rfc3339()),
+        ("uuid", []) => Some(uuid::Uuid::new_v4().to_string()),
+        ("randomInt", [low, high]) => {
+            let (low, high) = (low.parse::<i64>().ok()?, high.parse::<i64>().ok()?);
+            (low <= high).then(|| rand::thread_rng().gen_range(low..=high).to_string())
+        }
+        (name, []) => lookup(name.strip_prefix("request.")?, context),
+        _ => None,
+    }
+}
+
+/// Resolves a `request.*` path, `field` is what follows the `request.` prefix
+fn lookup(field: &str, context: &TemplateContext) -> Option<String> {
+    let request = context.request;
+    let (section, key) = match field.split_once('.') {
+        Some((section, key)) => (section, Some(key)),
+        None => (field, None),
+    };
+    match (section, key) {
+        ("method", None) => Some(request.method.to_string()),
+        ("path", None) => Some(request.path.clone()),
+        ("body", None) => Some(request.body_text().to_string()),
+        ("body", Some(field)) => Some(body_field(request, field)),
+        ("query", Some(name)) => Some(
+            request
+                .query_params()
+                .into_iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value)
+                .unwrap_or_default(),
+        ),
+        ("headers", Some(name)) => Some(request.headers.get(name).unwrap_or_default().to_string()),
+        ("params", Some(name)) => Some(context.params.get(name).cloned().unwrap_or_default()),
+        _ => None,
+    }
+}
+
+/// Looks up a field of a JSON body, either as a JSON pointer (`/user/name`) or with dots
+/// (`user.name`). Strings are rendered without their quotes.
+fn body_field(request: &Request, field: &str) -> String {
+    let pointer = match field.starts_with('/') {
+        true => field.to_string(),
+        false => format!("/{}", field.replace('.', "/")),
+    };
+    let body = serde_json::from_slice::<Value>(&request.body).unwrap_or_default();
+    match body.pointer(&pointer) {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Null) | None => String::new(),
+        Some(value) => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{Headers, Method};
+
+    #[test]
+    fn renders_request_fields() {
+        let mut headers = Headers::new();
+        headers.push("X-Trace", "abc");
+        let request = Request {
+            method: Method::POST,
+            path: "/users/7".to_string(),
+            query: Some("q=find%20me".to_string()),
+            version: "HTTP/1.1".to_string(),
+            headers,
+            body: br#"{"user": {"name": "Ada", "tags": ["a"]}}"#.to_vec(),
+        };
+        let params = IndexMap::from([("id".to_string(), "7".to_string())]);
+        let context = TemplateContext {
+            request: &request,
+            params: &params,
+        };
+        assert_eq!(
+            render(
+                "{{request.method}} {{ request.params.id }} {{request.query.q}} \
+                 {{request.headers.x-trace}} {{request.body.user.name}} {{request.body./user/tags}}",
+                &context
+            ),
+            r#"POST 7 find me abc Ada ["a"]"#
+        );
+        assert_eq!(
+            render("{{request.query.missing}}|{{nope}}|{{unclosed", &context),
+            "|{{nope}}|{{unclosed"
+        );
+        let number = render("{{randomInt 3 3}}", &context);
+        assert_eq!(number, "3");
+    }
+}
@@ -0,0 +1,185 @@
+use anyhow::{anyhow, Context, Result};
+use clap::Args;
+use rcgen::{BasicConstraints, CertificateParams, DnType, IsCa, KeyPair, KeyUsagePurpose};
+use serde::{Deserialize, Serialize};
+use std::{fmt::Display, fs, io::BufReader, path::Path, path::PathBuf, sync::Arc};
+use tokio_rustls::rustls::{
+    crypto::ring,
+    pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer},
+    server::WebPkiClientVerifier,
+    RootCertStore, ServerConfig, ServerConnection,
+};
+use tokio_rustls::TlsAcceptor;
+use x509_parser::prelude::{FromDer, X509Certificate};
+
+#[derive(Args, Debug, Clone)]
+pub struct Tls {
+    /// Certificate chain to serve https with (PEM)
+    #[arg(long, requires = "tls_key", conflicts_with = "tls_self_signed")]
+    pub tls_cert: Option<PathBuf>,
+    /// Private key of --tls-cert (PEM)
+    #[arg(long, requires = "tls_cert")]
+    pub tls_key: Option<PathBuf>,
+    /// Serve https with a certificate signed by a freshly generated CA
+    #[arg(long, default_value_t = false)]
+    pub tls_self_signed: bool,
+    /// Where the generated CA certificate is written for clients to trust
+    #[arg(long, default_value = "./testsuite-ca.pem")]
+    pub tls_ca_out: PathBuf,
+    /// Require client certificates signed by this CA (PEM)
+    #[arg(long)]
+    pub tls_client_ca: Option<PathBuf>,
+    /// Serve https on this port and plain http on --port
+    #[arg(long)]
+    pub tls_port: Option<u16>,
+}
+
+/// Negotiated TLS session of a connection
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TlsInfo {
+    pub version: String,
+    pub client: Option<ClientCertificate>,
+}
+
+/// Certificate a client authenticated with
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClientCertificate {
+    pub subject: String,
+    pub issuer: String,
+    pub serial: String,
+    pub not_after: String,
+}
+
+impl Tls {
+    pub fn enabled(&self) -> bool {
+        self.tls_cert.is_some() || self.tls_self_signed
+    }
+
+    /// Builds the acceptor for the configured certificates, `None` when TLS is off
+    pub fn acceptor(&self) -> Result<Option<TlsAcceptor>> {
+        if !self.enabled() && (self.tls_port.is_some() || self.tls_client_ca.is_some()) {
+            return Err(anyhow!(
+                "--tls-port and --tls-client-ca need --tls-cert or --tls-self-signed"
+            ));
+        }
+        let (chain, key) = match (&self.tls_cert, &self.tls_key, self.tls_self_signed) {
+            (Some(cert), Some(key), _) => (read_certificates(cert)?, read_key(key)?),
+            (_, _, true) => self.generate()?,
+            _ => return Ok(None),
+        };
+
+        let provider = Arc::new(ring::default_provider());
+        let builder = ServerConfig::builder_with_provider(Arc::clone(&provider))
+            .with_safe_default_protocol_versions()?;
+        let builder = match &self.tls_client_ca {
+            Some(client_ca) => {
+                let mut roots = RootCertStore::empty();
+                for certificate in read_certificates(client_ca)? {
+                    roots.add(certificate)?;
+                }
+                let verifier =
+                    WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider)
+                        .build()
+                        .map_err(|e| anyhow!("{}: {}", client_ca.display(), e))?;
+                builder.with_client_cert_verifier(verifier)
+            }
+            None => builder.with_no_client_auth(),
+        };
+        let mut config = builder.with_single_cert(chain, key)?;
+        config.alpn_protocols = vec![b"http/1.1".to_vec()];
+        Ok(Some(TlsAcceptor::from(Arc::new(config))))
+    }
+
+    /// Creates a CA and a certificate for localhost signed by it, writing the CA to
+    /// `tls_ca_out`
+    fn generate(&self) -> Result<(Vec<CertificateDer<'static>>, PrivateKeyDer<'static>)> {
+        let ca_key = KeyPair::generate()?;
+        let mut ca_params = CertificateParams::new(Vec::<String>::new())?;
+        ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        ca_params
+            .distinguished_name
+            .push(DnType::CommonName, "testsuite CA");
+        ca_params.key_usages = vec![
+            KeyUsagePurpose::KeyCertSign,
+            KeyUsagePurpose::CrlSign,
+            KeyUsagePurpose::DigitalSignature,
+        ];
+        let ca = ca_params.self_signed(&ca_key)?;
+
+        let key = KeyPair::generate()?;
+        let mut params = CertificateParams::new(vec![
+            "localhost".to_string(),
+            "127.0.0.1".to_string(),
+            "::1".to_string(),
+        ])?;
+        params
+            .distinguished_name
+            .push(DnType::CommonName, "localhost");
+        let certificate = params.signed_by(&key, &ca, &ca_key)?;
+
+        fs::write(&self.tls_ca_out, ca.pem())
+            .with_context(|| format!("Could not write CA to {}", self.tls_ca_out.display()))?;
+        Ok((
+            vec![certificate.der().clone(), ca.der().clone()],
+            PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(key.serialize_der())),
+        ))
+    }
+}
+
+fn read_certificates(path: &Path) -> Result<Vec<CertificateDer<'static>>> {
+    let file = fs::File::open(path).with_context(|| path.display().to_string())?;
+    let certificates = rustls_pemfile::certs(&mut BufReader::new(file))
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| path.display().to_string())?;
+    match certificates.is_empty() {
+        true => Err(anyhow!("{}: no certificates found", path.display())),
+        false => Ok(certificates),
+    }
+}
+
+fn read_key(path: &Path) -> Result<PrivateKeyDer<'static>> {
+    let file = fs::File::open(path).with_context(|| path.display().to_string())?;
+    rustls_pemfile::private_key(&mut BufReader::new(file))
+        .with_context(|| path.display().to_string())?
+        .ok_or_else(|| anyhow!("{}: no private key found", path.display()))
+}
+
+impl TlsInfo {
+    pub fn from_connection(connection: &ServerConnection) -> TlsInfo {
+        let version = match connection.protocol_version() {
+            Some(version) => format!("{:?}", version).replace('_', "."),
+            None => "TLS".to_string(),
+        };
+        let client = connection
+            .peer_certificates()
+            .and_then(|certificates| certificates.first())
+            .and_then(|certificate| X509Certificate::from_der(certificate).ok())
+            .map(|(_, certificate)| ClientCertificate {
+                subject: certificate.subject().to_string(),
+                issuer: certificate.issuer().to_string(),
+                serial: certificate.raw_serial_as_string(),
+                not_after: certificate.validity().not_after.to_string(),
+            });
+        TlsInfo { version, client }
+    }
+}
+
+impl Display for TlsInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.version)?;
+        if let Some(client) = &self.client {
+            write!(f, ", client {client}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for ClientCertificate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} (issuer {}, serial {}, expires {})",
+            self.subject, self.issuer, self.serial, self.not_after
+        )
+    }
+}
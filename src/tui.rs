@@ -1,21 +1,29 @@
 use crate::Connections;
 mod elements;
+mod filter;
 pub mod style;
 
-use chrono::Utc;
+use chrono::{Local, Utc};
 use crossterm::{
     event::Event,
-    event::{KeyCode, KeyModifiers},
+    event::{KeyCode, KeyEvent, KeyModifiers},
     QueueableCommand,
 };
 use elements::*;
+use filter::Filter;
 use futures::lock::Mutex;
 use itertools::Itertools;
-use nanohttp::Status;
 use std::io::{Stdout, Write};
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
 use std::sync::Arc;
-use testsuite::{ConnectionFailedError, ResponseFormat};
+use std::time::Duration;
+use testsuite::har::write_har;
+use testsuite::http::{Method, Request, Status};
+use testsuite::journal::Journal;
+use testsuite::routes::RouteTable;
+use testsuite::{ConnectionFailedError, ConnectionId};
+use tokio::sync::watch;
 
 use testsuite::Message;
 
@@ -28,7 +36,23 @@ pub struct TuiState {
     pub needs_update: bool,
     history: History,
     screen: Screen,
+    /// Search typed after '/', narrowing the lists to the requests it matches
     pub prompt: String,
+    /// Whether keys are typed into the prompt
+    searching: bool,
+    filter: Filter,
+    /// Server event shown in the status bar
+    pub notice: Option<String>,
+    detail_tab: DetailTab,
+    /// First row shown of the address list, the connection list and the detail screen
+    address_scroll: usize,
+    member_scroll: usize,
+    detail_scroll: usize,
+    /// Routes being served, for resetting their scenarios and sequences
+    routes: watch::Receiver<Arc<RouteTable>>,
+    /// Exchanges exported by 'e'
+    journal: Arc<Journal>,
+    har_out: PathBuf,
 }
 
 #[allow(dead_code)]
@@ -183,7 +207,12 @@ impl From<&Select> for usize {
 
 #[allow(dead_code)]
 impl TuiState {
-    pub async fn new(connections: Arc<Mutex<Connections>>) -> Self {
+    pub async fn new(
+        connections: Arc<Mutex<Connections>>,
+        routes: watch::Receiver<Arc<RouteTable>>,
+        journal: Arc<Journal>,
+        har_out: PathBuf,
+    ) -> Self {
         let window_size = crossterm::terminal::size().expect("window has a size");
 
         TuiState {
@@ -200,9 +229,28 @@ impl TuiState {
             },
             screen: Screen::List,
             prompt: String::new(),
+            searching: false,
+            filter: Filter::default(),
+            notice: None,
+            detail_tab: DetailTab::Response,
+            address_scroll: 0,
+            member_scroll: 0,
+            detail_scroll: 0,
+            routes,
+            journal,
+            har_out,
         }
     }
 
+    /// Goes back to the empty address list once the connections were cleared
+    pub fn clear_history(&mut self) {
+        self.history = History {
+            current: (Screen::List, Select::Addr(0)),
+            prev: vec![],
+        };
+        self.screen = Screen::List;
+    }
+
     async fn cache(connections: Arc<Mutex<Connections>>) -> Connections {
         let mut cache_to = Connections::new();
         {
@@ -212,9 +260,121 @@ impl TuiState {
         cache_to
     }
 
+    /// Rebuilds the cache from the requests that pass the filter, keeping the cursor on the
+    /// same address and entry while they are still listed
+    async fn refresh(&mut self) {
+        let address = self.selected_address();
+        let entry = self.selected_entry();
+        let mut connections = TuiState::cache(Arc::clone(&self.connections)).await;
+        if !self.filter.is_empty() {
+            connections
+                .values_mut()
+                .for_each(|entries| entries.retain(|entry| self.filter.matches(entry)));
+            connections.retain(|_, entries| !entries.is_empty());
+        }
+        self.connections_cache = connections;
+
+        let address = address.and_then(|address| self.connections_cache.get_index_of(&address));
+        let member = match (address, entry) {
+            (Some(address), Some(entry)) => self.connections_cache[address]
+                .iter()
+                .position(|listed| listed.is_same_entry(&entry)),
+            _ => None,
+        };
+        if address.is_none() && self.history.current.0 != Screen::List {
+            self.clear_history();
+        }
+        if member.is_none() && self.history.current.0 == Screen::Detail {
+            self.history.pop();
+        }
+        for select in self
+            .history
+            .prev
+            .iter_mut()
+            .chain([&mut self.history.current])
+        {
+            match (select.1, address, member) {
+                (Select::Addr(_), Some(address), _) => select.1 = Select::Addr(address),
+                (Select::Member(_), _, Some(member)) => select.1 = Select::Member(member),
+                _ => {}
+            }
+        }
+        let max_select_size = self.get_max_select_size();
+        let selected = usize::from(self.history.current.1);
+        self.history
+            .current
+            .1
+            .select(Some(selected), max_select_size);
+    }
+
+    /// Address picked on the address list, on this screen or an earlier one
+    fn selected_address(&self) -> Option<IpAddr> {
+        self.history
+            .prev
+            .iter()
+            .chain([&self.history.current])
+            .find_map(|(_, select)| match select {
+                Select::Addr(address) => self.connections_cache.get_index(*address),
+                _ => None,
+            })
+            .map(|(address, _)| *address)
+    }
+
+    /// Entry picked on the connection list, on this screen or an earlier one
+    fn selected_entry(&self) -> Option<TuiResponse> {
+        let entries = self.connections_cache.get(&self.selected_address()?)?;
+        self.history
+            .prev
+            .iter()
+            .chain([&self.history.current])
+            .find_map(|(_, select)| match select {
+                Select::Member(member) => entries.get(*member).cloned(),
+                _ => None,
+            })
+    }
+
+    /// Types into the search prompt while it is open, returning whether the key was taken
+    fn edit_prompt(&mut self, key: KeyEvent) -> bool {
+        if !self.searching {
+            return false;
+        }
+        match (key.code, key.modifiers) {
+            (KeyCode::Char(letter), KeyModifiers::NONE | KeyModifiers::SHIFT) => {
+                self.prompt.push(letter)
+            }
+            (KeyCode::Backspace, _) => {
+                if self.prompt.pop().is_none() {
+                    self.searching = false;
+                }
+            }
+            (KeyCode::Enter, _) => self.searching = false,
+            (KeyCode::Esc, _) => {
+                self.prompt.clear();
+                self.searching = false;
+            }
+            _ => return false,
+        }
+        self.filter = Filter::parse(&self.prompt);
+        self.needs_update = true;
+        true
+    }
+
+    /// Text of the status bar, the search prompt followed by the last notice
+    fn status_text(&self) -> Option<String> {
+        let search = match (self.searching, self.prompt.is_empty()) {
+            (true, _) => Some(format!("/{}█", self.prompt)),
+            (false, false) => Some(format!("Filter: {}   (/ to edit)", self.prompt)),
+            (false, true) => None,
+        };
+        match (search, &self.notice) {
+            (Some(search), Some(notice)) => Some(format!("{search}   {notice}")),
+            (search, notice) => search.or_else(|| notice.clone()),
+        }
+    }
+
     pub async fn render(&mut self, out: Arc<Mutex<Stdout>>) -> anyhow::Result<()> {
         if self.needs_update {
-            self.connections_cache = TuiState::cache(Arc::clone(&self.connections)).await;
+            self.refresh().await;
             self.needs_update = false;
         }
 
@@ -242,19 +402,26 @@ impl TuiState {
                 (Screen::List, selected_address) => {
                     let address_list_bounds = Rect {
                         cols: (0, self.window_size.cols.1),
-                        rows: (1, self.window_size.rows.1),
+                        rows: (1, self.content_end()),
                     };
 
                     let addresses = Arc::from(Mutex::new(
                         self.connections_cache.keys().copied().collect_vec(),
                     ));
 
+                    self.address_scroll = follow(
+                        self.address_scroll,
+                        selected_address.into(),
+                        self.page_size(),
+                        self.connections_cache.len(),
+                    );
                     let address_list = AddressList::default(
                         address_list_bounds,
                         addresses,
                         true,
                         selected_address.into(),
-                    );
+                    )
+                    .with_scroll(self.address_scroll, self.connections_cache.len());
 
                     {
                         address_list.render(Arc::clone(&out)).await?;
@@ -263,40 +430,55 @@ impl TuiState {
                 (Screen::Details, selected_detail) => {
                     let address_list_bounds = Rect {
                         cols: (0, self.window_size.cols.1.checked_div(3).unwrap_or(10)),
-                        rows: (1, self.window_size.rows.1),
+                        rows: (1, self.content_end()),
                     };
 
                     let connection_list_bounds = Rect {
                         cols: (address_list_bounds.width(), self.window_size.cols.1),
-                        rows: (1, self.window_size.rows.1),
+                        rows: (1, self.content_end()),
                     };
 
                     let mut connection_list_items: Vec<TuiResponse> = vec![];
 
-                    if let Some((_, Select::Addr(address))) = self.history.peek_prev(1) {
+                    if let Some(&(_, Select::Addr(address))) = self.history.peek_prev(1) {
                         let addresses = Arc::from(Mutex::new(
                             self.connections_cache.keys().copied().collect_vec(),
                         ));
 
+                        self.address_scroll = follow(
+                            self.address_scroll,
+                            address,
+                            self.page_size(),
+                            self.connections_cache.len(),
+                        );
                         let address_list =
-                            AddressList::default(address_list_bounds, addresses, false, *address);
+                            AddressList::default(address_list_bounds, addresses, false, address)
+                                .with_scroll(self.address_scroll, self.connections_cache.len());
 
                         {
                             address_list.render(Arc::clone(&out)).await?;
                         }
-                        if let Some((_, items)) = self.connections_cache.get_index(*address) {
+                        if let Some((_, items)) = self.connections_cache.get_index(address) {
                             for item in items.iter() {
                                 connection_list_items.push(item.to_owned())
                             }
                         }
                     }
 
+                    self.member_scroll = follow(
+                        self.member_scroll,
+                        selected_detail.into(),
+                        self.page_size(),
+                        connection_list_items.len(),
+                    );
+                    let items = connection_list_items.len();
                     let connection_list = ConnectionsList::default(
                         Arc::from(Mutex::from(connection_list_items)),
                         connection_list_bounds,
                         true,
                         selected_detail.into(),
-                    );
+                    )
+                    .with_scroll(self.member_scroll, items);
 
                     {
                         connection_list.render(Arc::clone(&out)).await?;
@@ -305,9 +487,10 @@ impl TuiState {
                 (Screen::Detail, _selected_detail) => {
                     let detail_bounds = Rect {
                         cols: (0, self.window_size.cols.1),
-                        rows: (1, self.window_size.rows.1),
+                        rows: (1, self.content_end()),
                     };
 
+                    let mut selected = None;
                     if let Some((Screen::List, Select::Addr(address))) = self.history.peek_prev(2) {
                         if let Some((address, responses)) =
                             self.connections_cache.get_index(*address)
@@ -316,27 +499,54 @@ impl TuiState {
                                 self.history.peek_prev(1)
                             {
                                 if let Some(response) = responses.get(*member) {
-                                    let response_content = &response.content;
-                                    if let Some(content) = response_content {
-                                        let content = content.trim().lines().collect_vec();
-                                        let content = Arc::from(Mutex::new(content));
-                                        let detail = DetailWindow::default(
-                                            detail_bounds,
-                                            content,
-                                            true,
-                                            address.to_string(),
-                                        );
-                                        let out = Arc::clone(&out);
-                                        detail.render(out).await?;
-                                    }
+                                    selected =
+                                        Some((*address, response.get_detail(self.detail_tab)));
                                 }
                             }
                         }
                     }
+                    if let Some((address, detail)) = selected {
+                        // Long lines are wrapped so every part of a body can be scrolled to
+                        let content = detail
+                            .trim()
+                            .lines()
+                            .flat_map(|line| wrap(line, detail_bounds.width()))
+                            .collect_vec();
+                        self.detail_scroll = self
+                            .detail_scroll
+                            .min(content.len().saturating_sub(self.page_size()));
+                        let lines = content.len();
+                        let content = Arc::from(Mutex::new(content));
+                        let tabs = match self.detail_tab {
+                            DetailTab::Request => "[Request]  Response",
+                            DetailTab::Response => "Request  [Response]",
+                        };
+                        let detail = DetailWindow::default(
+                            detail_bounds,
+                            content,
+                            true,
+                            format!("{address}   {tabs}   (Tab to switch)"),
+                        )
+                        .with_highlights(self.filter.highlights())
+                        .with_scroll(self.detail_scroll, lines);
+                        let out = Arc::clone(&out);
+                        detail.render(out).await?;
+                    }
                 }
             }
         }
 
+        if let Some(text) = self.status_text() {
+            let status_bar = StatusBar {
+                bounds: &Rect {
+                    cols: (0, self.window_size.cols.1),
+                    rows: (self.content_end(), self.window_size.rows.1),
+                },
+                text: &text,
+            };
+            status_bar.render(Arc::clone(&out)).await?;
+        }
+
         let mut out = out.lock().await;
         debug!("Connections cache: {:?}", self.connections_cache);
 
@@ -344,6 +554,40 @@ impl TuiState {
         Ok(())
     }
 
+    /// Last row available to the screens, the status bar takes the bottom row when shown
+    fn content_end(&self) -> usize {
+        match self.status_text() {
+            Some(_) => self.window_size.rows.1.saturating_sub(1),
+            None => self.window_size.rows.1,
+        }
+    }
+
+    /// Rows a list or the detail screen shows below its header
+    fn page_size(&self) -> usize {
+        self.content_end().saturating_sub(2).max(1)
+    }
+
+    /// Moves the cursor of the lists, or scrolls the text of the detail screen
+    fn move_cursor(&mut self, movement: Movement) {
+        let rows = match movement {
+            Movement::Up | Movement::Down => 1,
+            Movement::PageUp | Movement::PageDown => self.page_size(),
+            Movement::Top | Movement::Bottom => usize::MAX,
+        };
+        let max_select_size = self.get_max_select_size();
+        match (self.history.current.0, movement) {
+            (Screen::Detail, Movement::Up | Movement::PageUp | Movement::Top) => {
+                self.detail_scroll = self.detail_scroll.saturating_sub(rows)
+            }
+            // Brought back to the last page when rendering
+            (Screen::Detail, _) => self.detail_scroll = self.detail_scroll.saturating_add(rows),
+            (_, Movement::Up | Movement::PageUp | Movement::Top) => {
+                self.history.current.1.sub(rows)
+            }
+            (_, _) => self.history.current.1.add(rows, max_select_size),
+        }
+    }
+
     fn set_screen(&mut self, screen: Screen) {
         self.screen = screen
     }
@@ -382,7 +626,12 @@ pub async fn parse_cli_event(
 ) -> anyhow::Result<()> {
     if let Some(event) = event {
         let mut out = out.lock().await;
+        let prompted = match &event {
+            Event::Key(key) => tuistate.lock().await.edit_prompt(*key),
+            _ => false,
+        };
         match event {
+            Event::Key(_) if prompted => {}
             Event::Key(key) => {
                 let (letter, modifier) = (key.code, key.modifiers);
                 match (letter, modifier) {
@@ -390,16 +639,32 @@ pub async fn parse_cli_event(
                         *exit_reason = Some("Pressed ctrl+q".to_string());
                     }
                     (KeyCode::Up, KeyModifiers::NONE) => {
-                        let mut tuistate = tuistate.lock().await;
-                        tuistate.history.current.1.sub(1);
+                        tuistate.lock().await.move_cursor(Movement::Up);
                     }
                     (KeyCode::Down, KeyModifiers::NONE) => {
+                        tuistate.lock().await.move_cursor(Movement::Down);
+                    }
+                    (KeyCode::PageUp, KeyModifiers::NONE) => {
+                        tuistate.lock().await.move_cursor(Movement::PageUp);
+                    }
+                    (KeyCode::PageDown, KeyModifiers::NONE) => {
+                        tuistate.lock().await.move_cursor(Movement::PageDown);
+                    }
+                    (KeyCode::Home, KeyModifiers::NONE) => {
+                        tuistate.lock().await.move_cursor(Movement::Top);
+                    }
+                    (KeyCode::End, KeyModifiers::NONE) => {
+                        tuistate.lock().await.move_cursor(Movement::Bottom);
+                    }
+                    (KeyCode::Tab, KeyModifiers::NONE)
+                    | (KeyCode::Right, KeyModifiers::NONE)
+                    | (KeyCode::Left, KeyModifiers::NONE) => {
                         let mut tuistate = tuistate.lock().await;
-                        let max_select_size = tuistate.get_max_select_size();
-                        tuistate.history.current.1.add(1, max_select_size);
+                        if tuistate.history.current.0 == Screen::Detail {
+                            tuistate.detail_tab = tuistate.detail_tab.toggle();
+                            tuistate.detail_scroll = 0;
+                        }
                     }
-                    (KeyCode::Right, KeyModifiers::NONE) => {}
-                    (KeyCode::Left, KeyModifiers::NONE) => {}
                     (KeyCode::Enter, KeyModifiers::NONE) => {
                         let mut tuistate = tuistate.lock().await;
                         match tuistate.history.current.0 {
@@ -409,6 +674,7 @@ pub async fn parse_cli_event(
                                 }
                             }
                             Screen::Details => {
+                                tuistate.detail_scroll = 0;
                                 tuistate
                                     .history
                                     .push((Screen::Detail, Select::Unselectable));
@@ -416,6 +682,35 @@ pub async fn parse_cli_event(
                             Screen::Detail => {}
                         }
                     }
+                    (KeyCode::Char('/'), KeyModifiers::NONE) => {
+                        tuistate.lock().await.searching = true;
+                    }
+                    (KeyCode::Char('r'), KeyModifiers::NONE) => {
+                        let mut tuistate = tuistate.lock().await;
+                        let routes = Arc::clone(&tuistate.routes.borrow());
+                        routes.reset();
+                        tuistate.notice = Some(format!(
+                            "{} Scenarios and sequences reset",
+                            Local::now().format("%H:%M:%S")
+                        ));
+                    }
+                    (KeyCode::Char('e'), KeyModifiers::NONE) => {
+                        let mut tuistate = tuistate.lock().await;
+                        let exchanges = tuistate.journal.exchanges();
+                        let result = write_har(&tuistate.har_out, &exchanges);
+                        tuistate.notice = Some(format!(
+                            "{} {}",
+                            Local::now().format("%H:%M:%S"),
+                            match result {
+                                Ok(()) => format!(
+                                    "Exported {} exchanges to {}",
+                                    exchanges.len(),
+                                    tuistate.har_out.display()
+                                ),
+                                Err(err) => format!("{err:#}"),
+                            }
+                        ));
+                    }
                     (KeyCode::Esc, KeyModifiers::NONE) => {
                         tuistate.lock().await.history.pop();
                     }
@@ -445,23 +740,186 @@ pub async fn parse_cli_event(
 #[allow(dead_code)]
 pub struct TuiResponse {
     addr: SocketAddr,
+    connection: Option<ConnectionId>,
+    /// Position of the request on its connection, `None` for connection events
+    sequence: Option<usize>,
+    request: Option<Request>,
+    /// Matcher of the route that picked the response
+    matcher: Option<String>,
+    /// Latency and faults injected into the response
+    injected: Option<String>,
     content: Option<String>,
     time: String,
-    status: Option<nanohttp::Status>,
-    method: Option<nanohttp::Method>,
-    format: Option<ResponseFormat>,
+    status: Option<Status>,
+    method: Option<Method>,
+    /// Request target, with the query string
+    path: Option<String>,
+    /// Content-Type of the response without its parameters
+    content_type: Option<String>,
+    /// Length of the response body in bytes
+    size: Option<usize>,
+    /// Time taken to answer, including injected delays
+    elapsed: Option<Duration>,
+}
+
+/// Where a key moves the cursor of a list, or the text of the detail screen
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Movement {
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Top,
+    Bottom,
+}
+
+/// Pane shown on the detail screen
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DetailTab {
+    Request,
+    Response,
+}
+
+impl DetailTab {
+    fn toggle(self) -> Self {
+        match self {
+            DetailTab::Request => DetailTab::Response,
+            DetailTab::Response => DetailTab::Request,
+        }
+    }
 }
 
 impl TuiResponse {
-    fn get_response_as_line(&self) -> String {
-        if let Some(content) = &self.content {
+    /// Text shown for this entry on the detail screen
+    fn get_detail(&self, tab: DetailTab) -> String {
+        match tab {
+            DetailTab::Response => {
+                let content = self
+                    .content
+                    .clone()
+                    .unwrap_or_else(|| "No content".to_string());
+                let notes = [
+                    self.matcher
+                        .as_ref()
+                        .map(|matcher| format!("Picked by {matcher}")),
+                    self.injected
+                        .as_ref()
+                        .map(|injected| format!("Injected {injected}")),
+                ]
+                .into_iter()
+                .flatten()
+                .map(|note| note + "\n")
+                .collect::<String>();
+                match notes.is_empty() {
+                    true => content,
+                    false => format!("{notes}\n{content}"),
+                }
+            }
+            DetailTab::Request => match &self.request {
+                Some(request) => format!("Received: {}\n\n{}", self.time, request),
+                None => "No request recorded".to_string(),
+            },
+        }
+    }
+
+    /// Status the entry is coloured by in the connection list
+    pub fn status_code(&self) -> Option<u16> {
+        self.status.map(|status| status.code())
+    }
+
+    /// Whether `other` is this entry, taken from another copy of the connections
+    fn is_same_entry(&self, other: &TuiResponse) -> bool {
+        self.addr == other.addr
+            && self.connection == other.connection
+            && self.sequence == other.sequence
+            && self.time == other.time
+    }
+
+    /// Line shown in the connection list, `width` is the room the list has for it
+    fn get_response_as_line(&self, width: usize) -> String {
+        let content = if let Some(content) = &self.content {
             content.lines().map(|x| x.to_string() + " ").collect()
         } else {
             "No content".to_string()
+        };
+        match (self.connection, self.sequence) {
+            (Some(_), Some(sequence)) => {
+                let tags = [&self.matcher, &self.injected]
+                    .into_iter()
+                    .flatten()
+                    .map(|tag| format!("[{tag}] "))
+                    .collect::<String>();
+                let method = self.method.map(|method| method.to_string());
+                let status = self.status.map(|status| status.code().to_string());
+                let size = self.size.map(format_size);
+                let elapsed = self.elapsed.map(format_elapsed);
+                // The path takes whatever the other columns leave of the line
+                let path_width = width.saturating_sub(59).max(12);
+                format!(
+                    "  └ #{sequence:<3} {:<7} {:<path_width$} {:<3} {:<16} {:>8} {:>9}  {tags}",
+                    column(method.as_deref(), 7),
+                    column(self.path.as_deref(), path_width),
+                    column(status.as_deref(), 3),
+                    column(self.content_type.as_deref(), 16),
+                    column(size.as_deref(), 8),
+                    column(elapsed.as_deref(), 9),
+                )
+            }
+            (Some(connection), None) => format!("Connection {connection}: {content}"),
+            (None, _) => content,
         }
     }
 }
 
+/// First row shown of a list of `len` items with room for `rows`, moved just enough to keep
+/// the `selected` item in view
+fn follow(offset: usize, selected: usize, rows: usize, len: usize) -> usize {
+    offset
+        .min(selected)
+        .max((selected + 1).saturating_sub(rows))
+        .min(len.saturating_sub(rows))
+}
+
+/// Splits `line` into pieces of at most `width` characters
+fn wrap(line: &str, width: usize) -> Vec<&str> {
+    let mut pieces = vec![];
+    let mut rest = line;
+    while rest.chars().count() > width.max(1) {
+        let (end, _) = rest
+            .char_indices()
+            .nth(width.max(1))
+            .expect("line is longer than width");
+        pieces.push(&rest[..end]);
+        rest = &rest[end..];
+    }
+    pieces.push(rest);
+    pieces
+}
+
+/// Cell of a connection list column, cut to `width` with an ellipsis when the text is longer
+fn column(text: Option<&str>, width: usize) -> String {
+    let text = text.unwrap_or("-");
+    match text.chars().count() > width {
+        true => text.chars().take(width - 1).chain(['…']).collect(),
+        false => text.to_string(),
+    }
+}
+
+fn format_size(size: usize) -> String {
+    match size {
+        0..=1023 => format!("{size} B"),
+        1024..=1_048_575 => format!("{:.1} KB", size as f64 / 1024.0),
+        _ => format!("{:.1} MB", size as f64 / 1_048_576.0),
+    }
+}
+
+fn format_elapsed(elapsed: Duration) -> String {
+    match elapsed.as_millis() {
+        0..=999 => format!("{:.1} ms", elapsed.as_secs_f64() * 1000.0),
+        _ => format!("{:.2} s", elapsed.as_secs_f64()),
+    }
+}
+
 pub async fn handle_message(message: Message, connections: Arc<Mutex<Connections>>) {
     let mut connections = connections.lock().await;
     match message {
@@ -473,10 +931,18 @@ pub async fn handle_message(message: Message, connections: Arc<Mutex<Connections
                 Some(existing_connection) => existing_connection.push(TuiResponse {
                     content: Some(format!("Error: {:}", error.1)),
                     addr: error.0,
-                    format: None,
+                    connection: None,
+                    sequence: None,
+                    request: None,
+                    matcher: None,
+                    injected: None,
                     time: Utc::now().to_rfc3339(),
-                    status: Some(Status::InternalServerError),
+                    status: Some(Status::INTERNAL_SERVER_ERROR),
                     method: None,
+                    path: None,
+                    content_type: None,
+                    size: None,
+                    elapsed: None,
                 }),
                 None => {
                     connections.insert(
@@ -484,68 +950,95 @@ pub async fn handle_message(message: Message, connections: Arc<Mutex<Connections
                         vec![TuiResponse {
                             content: Some(format!("Error: {:}", error.1)),
                             addr: error.0,
-                            format: None,
+                            connection: None,
+                            sequence: None,
+                            request: None,
+                            matcher: None,
+                            injected: None,
                             time: Utc::now().to_rfc3339(),
-                            status: Some(Status::InternalServerError),
+                            status: Some(Status::INTERNAL_SERVER_ERROR),
                             method: None,
+                            path: None,
+                            content_type: None,
+                            size: None,
+                            elapsed: None,
                         }],
                     );
                 }
             },
         },
-        Message::Response(message) => match connections.get_mut(&message.addr.ip()) {
-            Some(existing_connection) => {
-                existing_connection.push(TuiResponse {
-                    status: None,
-                    method: None,
+        Message::Response(message) => {
+            let message = *message;
+            let entries = connections.entry(message.addr.ip()).or_default();
+            let sequence = entries
+                .iter()
+                .filter(|entry| entry.connection == Some(message.connection))
+                .filter(|entry| entry.sequence.is_some())
+                .count()
+                + 1;
+            push_to_connection(
+                entries,
+                TuiResponse {
+                    status: Some(message.response.status),
+                    method: Some(message.request.method),
+                    path: Some(message.request.target()),
+                    content_type: message
+                        .response
+                        .headers
+                        .get("Content-Type")
+                        .and_then(|content_type| content_type.split(';').next())
+                        .map(|content_type| content_type.trim().to_string()),
+                    size: Some(message.response.body.len()),
+                    elapsed: Some(message.elapsed),
                     addr: message.addr,
+                    connection: Some(message.connection),
+                    sequence: Some(sequence),
+                    request: Some(message.request),
+                    matcher: message.matcher,
+                    injected: message.injected,
                     content: Some(message.response.to_string()),
-                    format: Some(ResponseFormat::Json),
-                    time: Utc::now().to_rfc3339(),
-                });
-            }
-            None => {
-                connections.insert(
-                    message.addr.ip(),
-                    vec![TuiResponse {
+                    time: message.received.to_rfc3339(),
+                },
+            );
+        }
+        Message::ConnectionReceived(connection, id, tls) => {
+            if let Some(connection) = connection {
+                connections
+                    .entry(connection.ip())
+                    .or_default()
+                    .push(TuiResponse {
+                        addr: connection,
+                        connection: Some(id),
+                        sequence: None,
+                        request: None,
+                        matcher: None,
+                        injected: None,
                         status: None,
                         method: None,
-                        addr: message.addr,
-                        content: Some(message.response.to_string()),
-                        format: Some(ResponseFormat::Json),
+                        path: None,
+                        content_type: None,
+                        size: None,
+                        elapsed: None,
+                        content: Some(match tls {
+                            Some(tls) => format!("Established connection over {tls}"),
+                            None => "Established connection".to_string(),
+                        }),
                         time: Utc::now().to_rfc3339(),
-                    }],
-                );
-            }
-        },
-        Message::ConnectionReceived(connection) => {
-            if let Some(connection) = connection {
-                match connections.get_mut(&connection.ip()) {
-                    Some(existing_connection) => {
-                        existing_connection.push(TuiResponse {
-                            addr: connection,
-                            status: Some(Status::Ok),
-                            method: None,
-                            content: Some("Established Connection".to_string()),
-                            format: None,
-                            time: Utc::now().to_rfc3339(),
-                        });
-                    }
-                    None => {
-                        connections.insert(
-                            connection.ip(),
-                            vec![TuiResponse {
-                                status: Some(Status::Ok),
-                                method: None,
-                                addr: connection,
-                                content: Some("Established connection".to_string()),
-                                format: None,
-                                time: Utc::now().to_rfc3339(),
-                            }],
-                        );
-                    }
-                }
+                    });
             }
         }
+        // Not tied to a client, the message loop shows these in the status bar
+        Message::RoutesReloaded(_) | Message::Notice(_) | Message::HistoryCleared => {}
+    }
+}
+
+/// Places an entry after the last one of the same connection so keep-alive requests stay grouped
+fn push_to_connection(entries: &mut Vec<TuiResponse>, entry: TuiResponse) {
+    let position = entries.iter().rposition(|existing| {
+        existing.connection.is_some() && existing.connection == entry.connection
+    });
+    match position {
+        Some(position) => entries.insert(position + 1, entry),
+        None => entries.push(entry),
     }
 }
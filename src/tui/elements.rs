@@ -38,17 +38,12 @@ impl ListableItem for IpAddr {
 
 impl ListableItem for TuiResponse {
     fn print(&self, is_selected: bool, max_length: usize) -> StyledContent<String> {
-        let content = self.get_response_as_line();
-        match is_selected {
-            true => StyleVariants::get_styled_item(
-                self.size_text(&content, max_length),
-                StyleVariants::Selected(true),
-            ),
-            false => StyleVariants::get_styled_item(
-                self.size_text(&content, max_length),
-                StyleVariants::Selected(false),
-            ),
-        }
+        let content = self.get_response_as_line(max_length);
+        let variant = match (is_selected, self.status_code()) {
+            (false, Some(code)) => StyleVariants::Status(code),
+            (selected, _) => StyleVariants::Selected(selected),
+        };
+        StyleVariants::get_styled_item(self.size_text(&content, max_length), variant)
     }
 }
 
@@ -64,9 +59,34 @@ impl ListableItem for &str {
     }
 }
 
+/// Part of a list or text shown by an element, the first row after its header and the number
+/// of rows there are
+#[derive(Debug, Default, Clone, Copy)]
+struct Scroll {
+    offset: usize,
+    len: usize,
+}
+
+impl Scroll {
+    /// Rows shown out of all of them, added to the header when they do not all fit in `bounds`
+    fn indicator(&self, bounds: &Rect) -> String {
+        let rows = bounds.height().saturating_sub(1);
+        match self.len > rows {
+            true => format!(
+                "  [{}-{}/{}]",
+                self.offset + 1,
+                (self.offset + rows).min(self.len),
+                self.len
+            ),
+            false => String::new(),
+        }
+    }
+}
+
 #[async_trait]
 /// Trait for defining an UIElement as a list
 pub trait UiList<'a, T: ListableItem>: UiElement {
+    #[allow(dead_code)]
     fn new(items: Arc<Mutex<Vec<T>>>, bounds: Rect, current: bool, selected_item: usize) -> Self;
     async fn print(&self) -> Vec<StyledContent<String>>;
     fn bounds(&self) -> &Rect;
@@ -82,6 +102,7 @@ where
     list: Arc<Mutex<Vec<T>>>,
     current: bool,
     selected_item: usize,
+    scroll: Scroll,
 }
 
 #[async_trait]
@@ -92,6 +113,7 @@ impl<'a, T: ListableItem + Send + Sync> UiList<'a, T> for AddressList<T> {
             list: items,
             selected_item,
             current,
+            scroll: Scroll::default(),
         }
     }
     fn get_selected_index(&self) -> usize {
@@ -112,9 +134,10 @@ impl<'a, T: ListableItem + Send + Sync> UiList<'a, T> for AddressList<T> {
             .await
             .iter()
             .enumerate()
+            .skip(self.scroll.offset)
             .take(UiList::bounds(self).height())
             .for_each(|(index, address)| {
-                buffer[index] = address.print(
+                buffer[index - self.scroll.offset] = address.print(
                     index == self.get_selected_index(),
                     UiList::bounds(self).width(),
                 );
@@ -170,7 +193,11 @@ impl<T: ListableItem + Sync + Send> UiElement for AddressList<T> {
 
     fn get_header(&self, current: bool) -> StyledContent<String> {
         StyleVariants::get_styled_item(
-            format!("{:^len$}", "Address", len = UiElement::bounds(self).width()),
+            format!(
+                "{:^len$}",
+                format!("Address{}", self.scroll.indicator(UiElement::bounds(self))),
+                len = UiElement::bounds(self).width()
+            ),
             StyleVariants::Header(current),
         )
     }
@@ -188,6 +215,14 @@ impl<T: ListableItem + Send + Sync + Clone> AddressList<T> {
             current,
             bounds,
             selected_item,
+            scroll: Scroll::default(),
+        }
+    }
+
+    pub fn with_scroll(self, offset: usize, len: usize) -> Self {
+        AddressList {
+            scroll: Scroll { offset, len },
+            ..self
         }
     }
 }
@@ -198,6 +233,7 @@ pub struct ConnectionsList<T: ListableItem + Sync + Send> {
     list: Arc<Mutex<Vec<T>>>,
     current: bool,
     selected_item: usize,
+    scroll: Scroll,
 }
 
 #[async_trait]
@@ -208,6 +244,7 @@ impl<'a, T: ListableItem + Send + Sync> UiList<'a, T> for ConnectionsList<T> {
             list: items,
             current,
             selected_item,
+            scroll: Scroll::default(),
         }
     }
     fn bounds(&self) -> &Rect {
@@ -251,7 +288,10 @@ impl<T: ListableItem + Send + Sync> UiElement for ConnectionsList<T> {
         StyleVariants::get_styled_item(
             format!(
                 "{:^len$}",
-                "Connections",
+                format!(
+                    "Connections{}",
+                    self.scroll.indicator(UiElement::bounds(self))
+                ),
                 len = UiElement::bounds(self).width()
             ),
             StyleVariants::Header(current),
@@ -277,9 +317,11 @@ impl<T: ListableItem + std::marker::Sync + std::marker::Send> ConnectionsList<T>
             .await
             .iter()
             .enumerate()
+            .skip(self.scroll.offset)
             .take(UiElement::bounds(self).height())
             .for_each(|(index, item)| {
-                buffer[index] = item.print(selectedgroup == index, UiList::bounds(self).width())
+                buffer[index - self.scroll.offset] =
+                    item.print(selectedgroup == index, UiList::bounds(self).width())
             });
         buffer
     }
@@ -295,6 +337,14 @@ impl<T: ListableItem + std::marker::Sync + std::marker::Send> ConnectionsList<T>
             bounds,
             current,
             selected_item,
+            scroll: Scroll::default(),
+        }
+    }
+
+    pub fn with_scroll(self, offset: usize, len: usize) -> Self {
+        ConnectionsList {
+            scroll: Scroll { offset, len },
+            ..self
         }
     }
 }
@@ -327,12 +377,43 @@ impl UiElement for ProgramHeader<'_> {
     }
 }
 
+pub struct StatusBar<'a> {
+    pub bounds: &'a Rect,
+    pub text: &'a str,
+}
+
+#[async_trait]
+impl UiElement for StatusBar<'_> {
+    fn bounds(&self) -> &Rect {
+        self.bounds
+    }
+    async fn render(self, out: Out) -> anyhow::Result<()> {
+        let mut out = out.lock().await;
+
+        if let Some(next_line) = self.get_next_line(0) {
+            out.queue(next_line)?;
+            out.queue(PrintStyledContent(self.get_header(false)))?;
+        }
+        Ok(())
+    }
+    fn is_current(&self) -> bool {
+        false
+    }
+    fn get_header(&self, _current: bool) -> StyledContent<String> {
+        let width = self.bounds().width();
+        StyleVariants::get_styled_item(format!("{:width$.width$}", self.text), StyleVariants::Title)
+    }
+}
+
 #[derive(Debug)]
 pub struct DetailWindow<T> {
     bounds: Rect,
     current: bool,
     details: Arc<Mutex<Vec<T>>>,
     addr: String,
+    /// Lowercased text to mark wherever it shows up
+    highlights: Vec<String>,
+    scroll: Scroll,
 }
 
 #[async_trait]
@@ -349,7 +430,9 @@ impl<T: ListableItem + std::marker::Sync + std::marker::Send> UiElement for Deta
             for (line, content) in buffer.into_iter().enumerate() {
                 if let Some(next_line) = self.get_next_line(line + 1) {
                     out.queue(next_line)?;
-                    out.queue(PrintStyledContent(content))?;
+                    for part in highlight(content, &self.highlights) {
+                        out.queue(PrintStyledContent(part))?;
+                    }
                 }
             }
         }
@@ -361,7 +444,10 @@ impl<T: ListableItem + std::marker::Sync + std::marker::Send> UiElement for Deta
     fn get_header(&self, current: bool) -> StyledContent<String> {
         let width = UiElement::bounds(self).width();
         StyleVariants::get_styled_item(
-            format!("{:^width$}", &self.addr),
+            format!(
+                "{:^width$}",
+                self.addr.clone() + &self.scroll.indicator(&self.bounds)
+            ),
             StyleVariants::Header(current),
         )
     }
@@ -387,6 +473,8 @@ impl<'a, T: ListableItem + Send + Sync> UiList<'a, T> for DetailWindow<T> {
             current,
             details: items,
             addr: String::new(),
+            highlights: vec![],
+            scroll: Scroll::default(),
         }
     }
     async fn print(&self) -> Vec<StyledContent<String>> {
@@ -402,6 +490,7 @@ impl<'a, T: ListableItem + Send + Sync> UiList<'a, T> for DetailWindow<T> {
             .lock()
             .await
             .iter()
+            .skip(self.scroll.offset)
             .enumerate()
             .take(UiList::bounds(self).height())
             .for_each(|(index, line)| {
@@ -421,6 +510,54 @@ impl<T: ListableItem + Send + Sync + Clone> DetailWindow<T> {
             current,
             bounds,
             addr,
+            highlights: vec![],
+            scroll: Scroll::default(),
+        }
+    }
+
+    pub fn with_highlights(self, highlights: Vec<String>) -> Self {
+        DetailWindow { highlights, ..self }
+    }
+
+    pub fn with_scroll(self, offset: usize, len: usize) -> Self {
+        DetailWindow {
+            scroll: Scroll { offset, len },
+            ..self
         }
     }
 }
+
+/// Splits a line into the parts matching one of `highlights`, ignoring case, and the rest
+fn highlight(line: StyledContent<String>, highlights: &[String]) -> Vec<StyledContent<String>> {
+    let text = line.content();
+    let lowercase = text.to_ascii_lowercase();
+    let mut parts = vec![];
+    let (mut start, mut position) = (0, 0);
+    while position < text.len() {
+        let found = highlights
+            .iter()
+            .filter(|highlight| !highlight.is_empty())
+            .find(|highlight| lowercase[position..].starts_with(highlight.as_str()));
+        match found {
+            Some(found) => {
+                if start < position {
+                    parts.push(StyledContent::new(
+                        *line.style(),
+                        text[start..position].to_string(),
+                    ));
+                }
+                let end = position + found.len();
+                parts.push(StyleVariants::get_styled_item(
+                    text[position..end].to_string(),
+                    StyleVariants::Match,
+                ));
+                (start, position) = (end, end);
+            }
+            None => position += text[position..].chars().next().map_or(1, char::len_utf8),
+        }
+    }
+    if start < text.len() || parts.is_empty() {
+        parts.push(StyledContent::new(*line.style(), text[start..].to_string()));
+    }
+    parts
+}
@@ -0,0 +1,107 @@
+use crate::tui::TuiResponse;
+use regex::Regex;
+
+/// Search typed at the `/` prompt. Every term has to hold for an entry to be listed.
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    terms: Vec<Term>,
+}
+
+#[derive(Debug, Clone)]
+enum Term {
+    /// `status:5xx`, `status:404`, the digits before the `x`s are a prefix of the code
+    Status(Option<String>),
+    /// `method:post`, compared ignoring case and as a prefix so it narrows while typing
+    Method(String),
+    /// `path:/users*`, the whole path without the query, `*` matching any characters
+    Path(Regex),
+    /// Anything else, looked for in the request and response bodies ignoring case
+    Text(String),
+}
+
+impl Filter {
+    pub fn parse(input: &str) -> Self {
+        Filter {
+            terms: input.split_whitespace().map(Term::parse).collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// Whether `entry` is listed, only requests are kept while a filter is set
+    pub fn matches(&self, entry: &TuiResponse) -> bool {
+        match &entry.request {
+            Some(_) => self.terms.iter().all(|term| term.holds(entry)),
+            None => self.is_empty(),
+        }
+    }
+
+    /// Free text terms, lowercased, to highlight in the detail screen
+    pub fn highlights(&self) -> Vec<String> {
+        self.terms
+            .iter()
+            .filter_map(|term| match term {
+                Term::Text(text) => Some(text.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl Term {
+    fn parse(term: &str) -> Self {
+        match term.split_once(':') {
+            Some(("status", value)) => {
+                let prefix = value.to_ascii_lowercase();
+                let prefix = prefix.trim_end_matches('x');
+                Term::Status(
+                    (prefix.len() <= 3 && prefix.chars().all(|c| c.is_ascii_digit()))
+                        .then(|| prefix.to_string()),
+                )
+            }
+            Some(("method", value)) => Term::Method(value.to_ascii_uppercase()),
+            Some(("path", value)) => {
+                let pattern = value.split('*').map(regex::escape).collect::<Vec<_>>();
+                Term::Path(
+                    Regex::new(&format!("^{}$", pattern.join(".*")))
+                        .expect("escaped pattern is a valid regex"),
+                )
+            }
+            _ => Term::Text(term.to_ascii_lowercase()),
+        }
+    }
+
+    fn holds(&self, entry: &TuiResponse) -> bool {
+        match self {
+            Term::Status(prefix) => match (prefix, entry.status) {
+                (Some(prefix), Some(status)) => status.code().to_string().starts_with(prefix),
+                _ => false,
+            },
+            Term::Method(method) => entry
+                .method
+                .is_some_and(|found| found.to_string().starts_with(method)),
+            Term::Path(regex) => entry
+                .request
+                .as_ref()
+                .is_some_and(|request| regex.is_match(&request.path)),
+            Term::Text(text) => {
+                let request_body = entry
+                    .request
+                    .as_ref()
+                    .map(|request| request.body_text())
+                    .unwrap_or_default();
+                let response_body = entry
+                    .content
+                    .as_deref()
+                    .and_then(|content| content.split_once("\r\n\r\n"))
+                    .map(|(_, body)| body)
+                    .unwrap_or_default();
+                [request_body.as_ref(), response_body]
+                    .iter()
+                    .any(|body| body.to_ascii_lowercase().contains(text))
+            }
+        }
+    }
+}
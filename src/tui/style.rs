@@ -5,6 +5,10 @@ pub enum StyleVariants {
     Selected(bool),
     Header(bool),
     Title,
+    /// Unselected list entry coloured by the class of its status code
+    Status(u16),
+    /// Text matching the search
+    Match,
 }
 
 impl StyleVariants {
@@ -35,6 +39,29 @@ impl StyleVariants {
                 false => style(text).with(header).on(dark),
             },
             Self::Title => style(text).with(white).on(dark),
+            Self::Status(code) => {
+                let color = match code {
+                    200..=299 => Color::Rgb {
+                        r: 85,
+                        g: 255,
+                        b: 85,
+                    },
+                    300..=399 => Color::Rgb {
+                        r: 85,
+                        g: 255,
+                        b: 255,
+                    },
+                    400..=499 => header,
+                    500..=599 => Color::Rgb {
+                        r: 255,
+                        g: 85,
+                        b: 85,
+                    },
+                    _ => light,
+                };
+                style(text).with(color).on(dark)
+            }
+            Self::Match => style(text).with(dark).on(header),
         }
     }
 }
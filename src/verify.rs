@@ -0,0 +1,174 @@
+use crate::config::ConditionEntry;
+use crate::http::{read_response, Method, Request, Status};
+use crate::matcher::Condition;
+use crate::proxy::Upstream;
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::{
+    io::{AsyncWriteExt, BufReader},
+    net::TcpStream,
+};
+
+/// Largest admin API answer read back, the matched exchanges carry their bodies so this is
+/// well above the default request body limit
+const MAX_ANSWER_SIZE: usize = 256 * 1024 * 1024;
+
+/// Which recorded requests to look for. Every part that is set must hold, the conditions are
+/// the ones of route matchers.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct RequestPattern {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub method: Option<String>,
+    /// Path without the query string, compared exactly
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path_regex: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub when: Vec<ConditionEntry>,
+}
+
+/// [`RequestPattern`] ready to be checked against requests
+#[derive(Debug, Clone)]
+pub struct CompiledPattern {
+    method: Option<Method>,
+    path: Option<String>,
+    path_regex: Option<Regex>,
+    conditions: Vec<Condition>,
+}
+
+/// Recorded requests a pattern found, as returned by the admin API
+#[derive(Debug, Clone, Deserialize)]
+pub struct Verification {
+    pub count: usize,
+    /// The exchanges in the format of `GET /__admin/requests`
+    pub requests: Vec<Value>,
+}
+
+impl RequestPattern {
+    pub fn new() -> Self {
+        RequestPattern::default()
+    }
+
+    pub fn method(self, method: Method) -> Self {
+        RequestPattern {
+            method: Some(method.to_string()),
+            ..self
+        }
+    }
+
+    pub fn path(self, path: &str) -> Self {
+        RequestPattern {
+            path: Some(path.to_string()),
+            ..self
+        }
+    }
+
+    pub fn path_regex(self, regex: &str) -> Self {
+        RequestPattern {
+            path_regex: Some(regex.to_string()),
+            ..self
+        }
+    }
+
+    pub fn when(mut self, condition: ConditionEntry) -> Self {
+        self.when.push(condition);
+        self
+    }
+
+    /// Requires the header to have this value
+    pub fn header(self, name: &str, value: &str) -> Self {
+        self.when(ConditionEntry {
+            header: Some(name.to_string()),
+            equals: Some(value.to_string()),
+            ..Default::default()
+        })
+    }
+
+    /// Requires the body to contain `text`
+    pub fn body_contains(self, text: &str) -> Self {
+        self.when(ConditionEntry {
+            regex: Some(regex::escape(text)),
+            ..Default::default()
+        })
+    }
+
+    /// Requires the field at the JSON path of the body to have this value
+    pub fn json(self, path: &str, value: &str) -> Self {
+        self.when(ConditionEntry {
+            json: Some(path.to_string()),
+            equals: Some(value.to_string()),
+            ..Default::default()
+        })
+    }
+
+    pub fn compile(self) -> Result<CompiledPattern, String> {
+        Ok(CompiledPattern {
+            method: self
+                .method
+                .map(|method| method.to_ascii_uppercase().parse::<Method>())
+                .transpose()
+                .map_err(|e| e.to_string())?,
+            path: self.path,
+            path_regex: self
+                .path_regex
+                .map(|regex| Regex::new(&regex))
+                .transpose()
+                .map_err(|e| e.to_string())?,
+            conditions: self
+                .when
+                .into_iter()
+                .map(ConditionEntry::into_condition)
+                .collect::<Result<Vec<_>, _>>()?,
+        })
+    }
+}
+
+impl CompiledPattern {
+    pub fn matches(&self, request: &Request) -> bool {
+        self.method.is_none_or(|method| method == request.method)
+            && self.path.as_ref().is_none_or(|path| *path == request.path)
+            && self
+                .path_regex
+                .as_ref()
+                .is_none_or(|regex| regex.is_match(&request.path))
+            && self
+                .conditions
+                .iter()
+                .all(|condition| condition.holds(request))
+    }
+}
+
+/// Asks a running server for the requests it recorded that match `pattern`.
+///
+/// `admin` is the address of the admin API, like `http://localhost:8080/__admin`, the server
+/// has to run with `--admin`.
+pub async fn find_requests(admin: &str, pattern: &RequestPattern) -> Result<Verification> {
+    let admin = admin.parse::<Upstream>().map_err(|e| anyhow!(e))?;
+    let body = serde_json::to_vec(pattern)?;
+    let mut stream = TcpStream::connect((admin.host.as_str(), admin.port)).await?;
+    let head = format!(
+        "POST {}/requests/find HTTP/1.1\r\nHost: {}:{}\r\nContent-Type: application/json\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n",
+        admin.base,
+        admin.host,
+        admin.port,
+        body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(&body).await?;
+
+    let mut reader = BufReader::new(stream);
+    let response = read_response(&mut reader, Method::POST, MAX_ANSWER_SIZE).await?;
+    match response.status {
+        Status::OK => Ok(serde_json::from_slice(&response.body)?),
+        status => Err(anyhow!(
+            "Admin API answered {}: {}",
+            status,
+            response.body_text()
+        )),
+    }
+}